@@ -1,10 +1,11 @@
 mod session;
 
 use std::collections::HashSet;
+use std::num::NonZeroUsize;
 use crate::session::Session;
 use clap::{Parser};
 use coral_rs::api::generated::{Error, ResponseValue};
-use serenity::all::{ChannelId, Context, EventHandler, GatewayIntents, GuildChannel, Ready};
+use serenity::all::{ChannelId, Context, EventHandler, GatewayIntents, GuildChannel, Ready, RoleId};
 use serenity::prelude::TypeMapKey;
 use serenity::{async_trait, Client};
 use std::sync::{Arc};
@@ -32,6 +33,120 @@ struct Arguments {
     #[arg(long, env = "DISCORD_TIMEOUT")]
     timeout_duration: Option<humantime::Duration>,
 
+    /// A TOML file with the notifications posted while a support thread is inactive
+    #[arg(long, env = "DISCORD_TIMEOUT_STAGES")]
+    timeout_stages: Option<String>,
+
+    /// Whether support threads that time out are locked as well as archived
+    #[arg(long, env = "DISCORD_TIMEOUT_LOCK")]
+    timeout_lock: Option<bool>,
+
+    /// The name of the forum tag applied to support threads that time out
+    #[arg(long, env = "DISCORD_TIMEOUT_TAG")]
+    timeout_tag: Option<String>,
+
+    /// The name of the forum tag applied to threads that are closed by the support agent
+    #[arg(long, env = "DISCORD_RESOLVED_TAG")]
+    resolved_tag: Option<String>,
+
+    /// A comma separated list of forum tag names that the support agent is allowed to change
+    #[arg(long, env = "DISCORD_TAG_ALLOWLIST")]
+    tag_allowlist: Option<String>,
+
+    /// The role pinged when a support agent escalates a thread to staff
+    #[arg(long, env = "DISCORD_STAFF_ROLE_ID")]
+    staff_role_id: Option<RoleId>,
+
+    /// The channel that support agents post escalations to
+    #[arg(long, env = "DISCORD_STAFF_CHANNEL_ID")]
+    staff_channel_id: Option<ChannelId>,
+
+    /// The name of the forum tag applied to support threads escalated to staff
+    #[arg(long, env = "DISCORD_STAFF_TAG")]
+    staff_tag: Option<String>,
+
+    /// How long the satisfaction survey posted when a support thread closes stays open.  Set to 0s
+    /// to disable the survey
+    #[arg(long, env = "DISCORD_SURVEY_DURATION")]
+    survey_duration: Option<humantime::Duration>,
+
+    /// The file that survey responses are appended to.  This should be an absolute path, support
+    /// agents run in whichever working directory the Coral server gives them
+    #[arg(long, env = "DISCORD_SURVEY_STORE")]
+    survey_store: Option<String>,
+
+    /// A comma separated list of phrases that support agents are never allowed to send
+    #[arg(long, env = "DISCORD_BANNED_PHRASES")]
+    banned_phrases: Option<String>,
+
+    /// A file with the support agent's system prompt
+    #[arg(long, env = "DISCORD_SYSTEM_PROMPT")]
+    system_prompt: Option<String>,
+
+    /// The provider support agents run their model on: openrouter, openai or anthropic
+    #[arg(long, env = "DISCORD_PROVIDER")]
+    provider: Option<String>,
+
+    /// The base URL of the support agent's provider, e.g. a local OpenAI compatible server
+    #[arg(long, env = "DISCORD_BASE_URL")]
+    base_url: Option<String>,
+
+    /// The OpenAI API key, passed to support agents using the openai provider
+    #[arg(long, env = "OPENAI_API_KEY")]
+    openai_api_key: Option<String>,
+
+    /// The Anthropic API key, passed to support agents using the anthropic provider
+    #[arg(long, env = "ANTHROPIC_API_KEY")]
+    anthropic_api_key: Option<String>,
+
+    /// The id of the model support agents run on, as their provider names it
+    #[arg(long, env = "DISCORD_MODEL")]
+    model: Option<String>,
+
+    /// The id of a model support agents use if their main model is unavailable
+    #[arg(long, env = "DISCORD_FALLBACK_MODEL")]
+    fallback_model: Option<String>,
+
+    /// The sampling temperature of the support agent's model
+    #[arg(long, env = "DISCORD_TEMPERATURE")]
+    temperature: Option<f64>,
+
+    /// The maximum number of tokens the support agent's model can generate in one completion
+    #[arg(long, env = "DISCORD_MAX_TOKENS")]
+    max_tokens: Option<u64>,
+
+    /// The maximum number of thread messages given to a support agent in one turn
+    #[arg(long, env = "DISCORD_BATCH_SIZE")]
+    batch_size: Option<NonZeroUsize>,
+
+    /// How many tokens a support agent's conversation can grow to before it is summarized
+    #[arg(long, env = "DISCORD_CONTEXT_BUDGET")]
+    context_budget: Option<usize>,
+
+    /// How many of the most recent turns a support agent keeps verbatim when summarizing
+    #[arg(long, env = "DISCORD_CONTEXT_RECENT_TURNS")]
+    context_recent_turns: Option<NonZeroUsize>,
+
+    /// The maximum number of tokens in the summary of a support agent's older turns
+    #[arg(long, env = "DISCORD_SUMMARY_MAX_TOKENS")]
+    summary_max_tokens: Option<u64>,
+
+    /// A TOML file with model prices, used by support agents to log what a thread cost
+    #[arg(long, env = "DISCORD_PRICE_TABLE")]
+    price_table: Option<String>,
+
+    /// The most a support thread can cost in USD
+    #[arg(long, env = "DISCORD_BUDGET")]
+    budget: Option<f64>,
+
+    /// What a support agent does when its thread goes over budget: escalate or stop
+    #[arg(long, env = "DISCORD_BUDGET_ACTION")]
+    budget_action: Option<String>,
+
+    /// The directory support agents write thread transcripts to
+    #[arg(long, env = "DISCORD_TRANSCRIPT_DIR")]
+    transcript_dir: Option<String>,
+
     /// The OpenRouter API key
     #[arg(long, env = "OPENROUTER_API_KEY")]
     openrouter_api_key: String,
@@ -69,7 +184,7 @@ impl EventHandler for Handler {
         }
 
         let session = Session::new(
-            &arguments,
+            arguments,
             thread.id.to_string()
         );
 
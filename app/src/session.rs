@@ -33,10 +33,123 @@ impl<'a> Session<'a> {
         }
 
         if let Some(timeout) = self.arguments.timeout_duration {
-            options.insert("DISCORD_TIMEOUT_WARNING".to_string(),
+            options.insert("DISCORD_TIMEOUT".to_string(),
                            AgentOptionValue::String(format_duration(timeout.into()).to_string()));
         }
 
+        if let Some(stages) = &self.arguments.timeout_stages {
+            options.insert("DISCORD_TIMEOUT_STAGES".to_string(), AgentOptionValue::String(stages.clone()));
+        }
+
+        if let Some(lock) = self.arguments.timeout_lock {
+            options.insert("DISCORD_TIMEOUT_LOCK".to_string(), AgentOptionValue::String(lock.to_string()));
+        }
+
+        if let Some(tag) = &self.arguments.timeout_tag {
+            options.insert("DISCORD_TIMEOUT_TAG".to_string(), AgentOptionValue::String(tag.clone()));
+        }
+
+        if let Some(tag) = &self.arguments.resolved_tag {
+            options.insert("DISCORD_RESOLVED_TAG".to_string(), AgentOptionValue::String(tag.clone()));
+        }
+
+        if let Some(tags) = &self.arguments.tag_allowlist {
+            options.insert("DISCORD_TAG_ALLOWLIST".to_string(), AgentOptionValue::String(tags.clone()));
+        }
+
+        if let Some(role) = self.arguments.staff_role_id {
+            options.insert("DISCORD_STAFF_ROLE_ID".to_string(), AgentOptionValue::String(role.to_string()));
+        }
+
+        if let Some(channel) = self.arguments.staff_channel_id {
+            options.insert("DISCORD_STAFF_CHANNEL_ID".to_string(), AgentOptionValue::String(channel.to_string()));
+        }
+
+        if let Some(tag) = &self.arguments.staff_tag {
+            options.insert("DISCORD_STAFF_TAG".to_string(), AgentOptionValue::String(tag.clone()));
+        }
+
+        if let Some(duration) = self.arguments.survey_duration {
+            options.insert("DISCORD_SURVEY_DURATION".to_string(),
+                           AgentOptionValue::String(format_duration(duration.into()).to_string()));
+        }
+
+        if let Some(store) = &self.arguments.survey_store {
+            options.insert("DISCORD_SURVEY_STORE".to_string(), AgentOptionValue::String(store.clone()));
+        }
+
+        if let Some(phrases) = &self.arguments.banned_phrases {
+            options.insert("DISCORD_BANNED_PHRASES".to_string(), AgentOptionValue::String(phrases.clone()));
+        }
+
+        if let Some(prompt) = &self.arguments.system_prompt {
+            options.insert("DISCORD_SYSTEM_PROMPT".to_string(), AgentOptionValue::String(prompt.clone()));
+        }
+
+        if let Some(provider) = &self.arguments.provider {
+            options.insert("DISCORD_PROVIDER".to_string(), AgentOptionValue::String(provider.clone()));
+        }
+
+        if let Some(base_url) = &self.arguments.base_url {
+            options.insert("DISCORD_BASE_URL".to_string(), AgentOptionValue::String(base_url.clone()));
+        }
+
+        if let Some(key) = &self.arguments.openai_api_key {
+            options.insert("OPENAI_API_KEY".to_string(), AgentOptionValue::String(key.clone()));
+        }
+
+        if let Some(key) = &self.arguments.anthropic_api_key {
+            options.insert("ANTHROPIC_API_KEY".to_string(), AgentOptionValue::String(key.clone()));
+        }
+
+        if let Some(model) = &self.arguments.model {
+            options.insert("DISCORD_MODEL".to_string(), AgentOptionValue::String(model.clone()));
+        }
+
+        if let Some(model) = &self.arguments.fallback_model {
+            options.insert("DISCORD_FALLBACK_MODEL".to_string(), AgentOptionValue::String(model.clone()));
+        }
+
+        if let Some(temperature) = self.arguments.temperature {
+            options.insert("DISCORD_TEMPERATURE".to_string(), AgentOptionValue::String(temperature.to_string()));
+        }
+
+        if let Some(max_tokens) = self.arguments.max_tokens {
+            options.insert("DISCORD_MAX_TOKENS".to_string(), AgentOptionValue::String(max_tokens.to_string()));
+        }
+
+        if let Some(batch_size) = self.arguments.batch_size {
+            options.insert("DISCORD_BATCH_SIZE".to_string(), AgentOptionValue::String(batch_size.to_string()));
+        }
+
+        if let Some(budget) = self.arguments.context_budget {
+            options.insert("DISCORD_CONTEXT_BUDGET".to_string(), AgentOptionValue::String(budget.to_string()));
+        }
+
+        if let Some(turns) = self.arguments.context_recent_turns {
+            options.insert("DISCORD_CONTEXT_RECENT_TURNS".to_string(), AgentOptionValue::String(turns.to_string()));
+        }
+
+        if let Some(max_tokens) = self.arguments.summary_max_tokens {
+            options.insert("DISCORD_SUMMARY_MAX_TOKENS".to_string(), AgentOptionValue::String(max_tokens.to_string()));
+        }
+
+        if let Some(prices) = &self.arguments.price_table {
+            options.insert("DISCORD_PRICE_TABLE".to_string(), AgentOptionValue::String(prices.clone()));
+        }
+
+        if let Some(budget) = self.arguments.budget {
+            options.insert("DISCORD_BUDGET".to_string(), AgentOptionValue::String(budget.to_string()));
+        }
+
+        if let Some(action) = &self.arguments.budget_action {
+            options.insert("DISCORD_BUDGET_ACTION".to_string(), AgentOptionValue::String(action.clone()));
+        }
+
+        if let Some(dir) = &self.arguments.transcript_dir {
+            options.insert("DISCORD_TRANSCRIPT_DIR".to_string(), AgentOptionValue::String(dir.clone()));
+        }
+
         GraphAgentRequest {
             blocking: Some(true),
             coral_plugins: vec![],
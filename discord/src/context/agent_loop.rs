@@ -0,0 +1,80 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use coral_rs::agent::Agent;
+use coral_rs::agent_loop::DEFAULT_ITERATION_TOOL_QUOTA;
+use coral_rs::completion_evaluated_prompt::CompletionEvaluatedPrompt;
+use coral_rs::error::Error;
+use coral_rs::rig::completion::CompletionModel;
+use futures::{Stream, StreamExt};
+use tracing::{info, warn};
+use crate::context::ContextManager;
+use crate::context::summarizer::Summarizer;
+use crate::transcript::Transcript;
+
+/// The same loop as [`coral_rs::agent_loop::AgentLoop`], except that the conversation is held by a
+/// [`ContextManager`] which keeps it within its budget between turns.  Everything each completion
+/// adds to the conversation is recorded in the [`Transcript`] before it can be summarized
+pub struct ContextAgentLoop<M: CompletionModel, S: Summarizer> {
+    agent: Agent<M>,
+    context: ContextManager<S>,
+    transcript: Arc<Transcript>,
+    prompt_stream: Pin<Box<dyn Stream<Item = CompletionEvaluatedPrompt>>>,
+}
+
+impl<M: CompletionModel, S: Summarizer> ContextAgentLoop<M, S> {
+    pub fn new(
+        agent: Agent<M>,
+        context: ContextManager<S>,
+        transcript: Arc<Transcript>,
+        prompt_stream: impl Stream<Item = CompletionEvaluatedPrompt> + 'static
+    ) -> Self {
+        Self {
+            agent,
+            context,
+            transcript,
+            prompt_stream: Box::pin(prompt_stream),
+        }
+    }
+
+    /// Executes the loop, consuming self
+    pub async fn execute(mut self) -> Result<(), Error> {
+        info!("Starting Coral agent loop");
+
+        let mut iterations = 0;
+        while let Some(prompt) = self.prompt_stream.next().await {
+            iterations += 1;
+
+            // An iteration should always start with the loop prompt
+            self.context.start_turn(prompt.evaluate().await?.into()).await;
+
+            let mut depth = 0;
+            loop {
+                depth += 1;
+                info!("Tool iteration {depth}/{} [prompt iteration {iterations}]",
+                    DEFAULT_ITERATION_TOOL_QUOTA.map_or("unlimited".to_string(), |x| x.to_string()),
+                );
+
+                let messages = self.context.take_messages();
+                let before = messages.len();
+                let res = self.agent.run_completion(messages).await?;
+                self.transcript.record_completion(&res.messages[before..]);
+                if !res.texts.is_empty() {
+                    info!("\"{}\"", res.texts.join(""));
+                }
+
+                self.context.set_messages(res.messages);
+                if res.tools_used == 0 {
+                    info!("Prompt iteration [{iterations}] finished - no tools used");
+                    break;
+                }
+
+                if Some(depth) == DEFAULT_ITERATION_TOOL_QUOTA {
+                    warn!("Prompt iteration [{iterations}] finished - tool quota reached");
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
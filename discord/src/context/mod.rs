@@ -0,0 +1,265 @@
+pub mod summarizer;
+pub mod agent_loop;
+
+use coral_rs::rig::completion::{AssistantContent, Message};
+use coral_rs::rig::message::{ToolResultContent, UserContent};
+use tracing::{error, info, warn};
+use crate::context::summarizer::Summarizer;
+
+/// Roughly how many characters make up a token.  Providers count tokens differently, an estimate
+/// is close enough to decide when to compact
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimates the number of tokens in `messages`
+pub fn estimate_tokens(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .flat_map(serde_json::to_string)
+        .map(|message| message.len())
+        .sum::<usize>() / CHARS_PER_TOKEN
+}
+
+/// Renders `messages` as plain text for the summarizer
+fn transcript(messages: &[Message]) -> String {
+    let mut lines = Vec::new();
+    for message in messages {
+        match message {
+            Message::User { content } => lines.extend(content.iter().map(|content| match content {
+                UserContent::Text(text) => format!("Prompt: {}", text.text),
+                UserContent::ToolResult(result) => format!("Tool result: {}", result.content
+                    .iter()
+                    .map(|content| match content {
+                        ToolResultContent::Text(text) => text.text.as_str(),
+                        ToolResultContent::Image(_) => "[image]",
+                    })
+                    .collect::<Vec<_>>()
+                    .join("")),
+                _ => "Prompt: [media]".to_string(),
+            })),
+            Message::Assistant { content, .. } => lines.extend(content.iter().filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(format!("Agent: {}", text.text)),
+                AssistantContent::ToolCall(call) => Some(format!("Agent called {} with {}", call.function.name, call.function.arguments)),
+                AssistantContent::Reasoning(_) => None,
+            })),
+        }
+    }
+
+    lines.join("\n")
+}
+
+pub struct ContextOptions {
+    /// How many tokens the conversation can grow to before older turns are summarized.  The preamble
+    /// is not counted, it is sent with every completion whatever the size of the conversation
+    pub budget: usize,
+
+    /// How many of the most recent turns are always kept verbatim, at least one
+    pub recent_turns: usize,
+}
+
+/// Keeps the agent's conversation within a token budget.  Between turns, if the conversation is over
+/// budget, every turn except the most recent ones is folded into a running summary.
+///
+/// The first prompt, which has the owner's original question, is always kept verbatim.  The thread
+/// title is in the preamble, which is not part of the conversation.
+///
+/// ```text
+/// [first prompt] [summary] [turn] [turn] ... [turn]
+/// └──────── pinned ──────┘ └─── recent turns ─────┘
+/// ```
+pub struct ContextManager<S: Summarizer> {
+    summarizer: S,
+    options: ContextOptions,
+    messages: Vec<Message>,
+
+    /// How many messages at the start are never summarized: the first prompt, and the summary once
+    /// there is one
+    pinned: usize,
+
+    /// Where each turn starts in `messages`.  The first prompt is pinned, so the first turn starts
+    /// after it
+    turns: Vec<usize>,
+    summary: Option<String>,
+}
+
+impl<S: Summarizer> ContextManager<S> {
+    pub fn new(summarizer: S, options: ContextOptions) -> Self {
+        Self {
+            summarizer,
+            options,
+            messages: Vec::new(),
+            pinned: 0,
+            turns: Vec::new(),
+            summary: None,
+        }
+    }
+
+    /// Starts a turn with `prompt`, compacting the conversation first if it is over budget
+    pub async fn start_turn(&mut self, prompt: Message) {
+        if self.messages.is_empty() {
+            self.messages.push(prompt);
+            self.pinned = 1;
+            self.turns.push(1);
+            return;
+        }
+
+        self.compact().await;
+        self.turns.push(self.messages.len());
+        self.messages.push(prompt);
+    }
+
+    /// Takes the conversation for a completion, it must be given back with
+    /// [`ContextManager::set_messages`] with the completion's messages appended
+    pub fn take_messages(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.messages)
+    }
+
+    pub fn set_messages(&mut self, messages: Vec<Message>) {
+        self.messages = messages;
+    }
+
+    async fn compact(&mut self) {
+        let tokens = estimate_tokens(&self.messages);
+        if tokens <= self.options.budget || self.turns.len() <= self.options.recent_turns {
+            return;
+        }
+
+        let summarized_turns = self.turns.len() - self.options.recent_turns;
+        let cut = self.turns[summarized_turns];
+        let transcript = transcript(&self.messages[self.pinned..cut]);
+        let summary = match self.summarizer.summarize(self.summary.as_deref(), &transcript).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                error!("Failed to summarize the conversation, it was not compacted: {e}");
+                return;
+            }
+        };
+
+        let recent = self.messages.split_off(cut);
+        self.messages.truncate(1);
+        self.messages.push(Message::user(format!(
+            "[START OF CONVERSATION SUMMARY]\n{summary}\n[END OF CONVERSATION SUMMARY]"
+        )));
+
+        self.pinned = 2;
+        self.turns = self.turns[summarized_turns..]
+            .iter()
+            .map(|start| start - cut + self.pinned)
+            .collect();
+        self.messages.extend(recent);
+        self.summary = Some(summary);
+
+        let compacted = estimate_tokens(&self.messages);
+        info!("Summarized {summarized_turns} turns, the conversation went from ~{tokens} to ~{compacted} tokens");
+        if compacted > self.options.budget {
+            warn!("The conversation is still over its budget of {} tokens after summarizing", self.options.budget);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use coral_rs::rig::completion::CompletionError;
+
+    /// Records what it was asked to summarize and returns a numbered summary
+    #[derive(Default)]
+    struct FakeSummarizer {
+        calls: Mutex<Vec<(Option<String>, String)>>,
+        fail: bool,
+    }
+
+    impl Summarizer for FakeSummarizer {
+        async fn summarize(&self, summary: Option<&str>, transcript: &str) -> Result<String, CompletionError> {
+            if self.fail {
+                return Err(CompletionError::ProviderError("unavailable".to_string()));
+            }
+
+            let mut calls = self.calls.lock().unwrap();
+            calls.push((summary.map(str::to_string), transcript.to_string()));
+            Ok(format!("summary {}", calls.len()))
+        }
+    }
+
+    fn manager(budget: usize, summarizer: FakeSummarizer) -> ContextManager<FakeSummarizer> {
+        ContextManager::new(summarizer, ContextOptions {
+            budget,
+            recent_turns: 2,
+        })
+    }
+
+    /// Runs a turn where the agent answers `prompt` with a message of its own
+    async fn turn(manager: &mut ContextManager<FakeSummarizer>, prompt: &str) {
+        manager.start_turn(Message::user(prompt)).await;
+        let mut messages = manager.take_messages();
+        messages.push(Message::assistant(format!("answer to {prompt}")));
+        manager.set_messages(messages);
+    }
+
+    fn texts(manager: &ContextManager<FakeSummarizer>) -> Vec<String> {
+        transcript(&manager.messages)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn under_budget_is_left_alone() {
+        let mut manager = manager(usize::MAX, FakeSummarizer::default());
+        for i in 0..5 {
+            turn(&mut manager, &format!("question {i}")).await;
+        }
+
+        assert_eq!(manager.messages.len(), 10);
+        assert!(manager.summarizer.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn over_budget_keeps_first_prompt_and_recent_turns() {
+        let mut manager = manager(0, FakeSummarizer::default());
+        for i in 0..5 {
+            turn(&mut manager, &format!("question {i}")).await;
+        }
+
+        assert_eq!(texts(&manager), vec![
+            "Prompt: question 0",
+            "Prompt: [START OF CONVERSATION SUMMARY]",
+            "summary 2",
+            "[END OF CONVERSATION SUMMARY]",
+            "Prompt: question 2",
+            "Agent: answer to question 2",
+            "Prompt: question 3",
+            "Agent: answer to question 3",
+            "Prompt: question 4",
+            "Agent: answer to question 4",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn summary_is_carried_into_the_next_compaction() {
+        let mut manager = manager(0, FakeSummarizer::default());
+        for i in 0..5 {
+            turn(&mut manager, &format!("question {i}")).await;
+        }
+
+        let calls = manager.summarizer.calls.lock().unwrap();
+        assert_eq!(*calls, vec![
+            (None, "Agent: answer to question 0".to_string()),
+            (Some("summary 1".to_string()), "Prompt: question 1\nAgent: answer to question 1".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn failed_summary_leaves_conversation_intact() {
+        let mut manager = manager(0, FakeSummarizer {
+            fail: true,
+            ..Default::default()
+        });
+        for i in 0..5 {
+            turn(&mut manager, &format!("question {i}")).await;
+        }
+
+        assert_eq!(manager.messages.len(), 10);
+        assert_eq!(manager.summary, None);
+    }
+}
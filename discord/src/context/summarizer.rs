@@ -0,0 +1,68 @@
+use std::future::Future;
+use coral_rs::rig::completion::{AssistantContent, CompletionError, CompletionModel, Message};
+
+const SUMMARY_PREAMBLE: &str = r#"
+You summarize the earlier part of a conversation between a support agent, the user it is assisting in
+a Discord support thread and the other agents it consulted, so that the support agent can carry on
+without the full conversation.
+
+Keep every detail that may matter later: what the user asked and any follow-up questions, versions,
+operating systems, error messages, what was tried and whether it worked, answers already given,
+links shared and anything still unresolved.  Leave out greetings and small talk.  If there is a
+summary so far, return it updated with the new part of the conversation.  Respond with the summary
+only.
+"#;
+
+/// Turns the earlier part of a conversation into a summary for [`crate::context::ContextManager`]
+pub trait Summarizer: Send + Sync {
+    /// Summarizes `transcript`, folding in the `summary` of anything before it
+    fn summarize(&self, summary: Option<&str>, transcript: &str) -> impl Future<Output = Result<String, CompletionError>> + Send;
+}
+
+/// Summarizes with the same model the agent runs on
+pub struct ModelSummarizer<M: CompletionModel> {
+    model: M,
+    max_tokens: u64,
+    additional_params: Option<serde_json::Value>,
+}
+
+impl<M: CompletionModel> ModelSummarizer<M> {
+    pub fn new(model: M, max_tokens: u64, additional_params: Option<serde_json::Value>) -> Self {
+        Self {
+            model,
+            max_tokens,
+            additional_params,
+        }
+    }
+}
+
+impl<M: CompletionModel> Summarizer for ModelSummarizer<M> {
+    async fn summarize(&self, summary: Option<&str>, transcript: &str) -> Result<String, CompletionError> {
+        let mut prompt = String::new();
+        if let Some(summary) = summary {
+            prompt.push_str("# Summary so far\n");
+            prompt.push_str(summary);
+            prompt.push_str("\n\n");
+        }
+
+        prompt.push_str("# Conversation\n");
+        prompt.push_str(transcript);
+
+        let response = self.model
+            .completion_request(Message::user(prompt))
+            .preamble(SUMMARY_PREAMBLE.to_string())
+            .max_tokens(self.max_tokens)
+            .additional_params_opt(self.additional_params.clone())
+            .send()
+            .await?;
+
+        Ok(response.choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(""))
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use clap::ValueEnum;
+use serenity::all::{CreateEmbed, CreateMessage, GuildChannel, Http};
+use tracing::{error, warn};
+use crate::discord::escalation::Escalation;
+use crate::discord::shutdown::{Shutdown, ShutdownReason};
+use crate::usage::UsageTracker;
+
+/// What happens when a thread goes over its budget
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetAction {
+    /// Hand the thread to staff.  If there is nowhere to escalate to, the agent stops
+    Escalate,
+
+    /// Stop the agent, leaving the thread open for staff
+    Stop,
+}
+
+/// Limits how much the agent can spend on a thread.  The budget is checked between turns, once it is
+/// exceeded the thread is escalated or the agent stops.  An escalated thread that staff hand back
+/// to the agent is not escalated again.
+pub struct Budget {
+    usage: Arc<UsageTracker>,
+    limit: Option<f64>,
+    action: BudgetAction,
+    escalation: Arc<Escalation>,
+    shutdown: Arc<Shutdown>,
+    http: Arc<Http>,
+    channel: GuildChannel,
+    exceeded: AtomicBool,
+}
+
+impl Budget {
+    pub fn new(
+        usage: Arc<UsageTracker>,
+        limit: Option<f64>,
+        action: BudgetAction,
+        escalation: Arc<Escalation>,
+        shutdown: Arc<Shutdown>,
+        http: Arc<Http>,
+        channel: GuildChannel,
+    ) -> Self {
+        Self {
+            usage,
+            limit,
+            action,
+            escalation,
+            shutdown,
+            http,
+            channel,
+            exceeded: AtomicBool::new(false),
+        }
+    }
+
+    /// Finishes the usage of the turn and checks the budget.  Returns false if the agent has to
+    /// stop
+    pub async fn finish_turn(&self) -> bool {
+        self.usage.finish_turn();
+
+        let (Some(limit), Some(cost)) = (self.limit, self.usage.cost()) else {
+            return true;
+        };
+
+        if cost <= limit || self.exceeded.swap(true, Ordering::SeqCst) {
+            return true;
+        }
+
+        warn!("Thread cost ${cost:.4}, which is over its budget of ${limit:.2}");
+        if self.action == BudgetAction::Escalate && self.escalation.is_configured() {
+            match self.escalation.escalate(format!("This thread went over its budget of ${limit:.2}")).await {
+                Ok(_) => return true,
+                Err(e) => error!("Error escalating a thread over budget, stopping instead: {e}"),
+            }
+        }
+
+        let embed = CreateEmbed::new()
+            .title("⛔ Usage limit reached")
+            .description("This thread has reached its usage limit, so automatic replies have stopped.  \
+                A member of staff can still help here.");
+        if let Err(e) = self.channel.send_message(&self.http, CreateMessage::new().embed(embed)).await {
+            error!("Error sending usage limit message: {e}");
+        }
+
+        self.shutdown.shutdown(ShutdownReason::BudgetExceeded).await;
+        false
+    }
+}
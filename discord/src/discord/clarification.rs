@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use serenity::all::{ButtonStyle, ComponentInteraction, ComponentInteractionDataKind, Context, CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, GuildChannel, Http, Message, MessageId, UserId};
+use serenity::prelude::TypeMapKey;
+use tokio::sync::Mutex;
+use tracing::{error, warn};
+use crate::discord::thread_message::{ThreadChoice, ThreadMessage};
+use crate::discord::thread_watcher::ThreadWatcher;
+
+const CLARIFY_ID_PREFIX: &str = "clarify:";
+const CLARIFY_SELECT_ID: &str = "clarify:select";
+
+// https://discord.com/developers/docs/interactions/message-components#action-rows
+const BUTTONS_PER_ROW: usize = 5;
+
+#[derive(Clone, Copy)]
+pub enum ChoiceStyle {
+    Buttons,
+    Select,
+}
+
+pub struct ChoiceOption {
+    pub label: String,
+    pub value: String,
+    pub description: Option<String>,
+}
+
+struct ChoicePrompt {
+    question: String,
+    options: Vec<ChoiceOption>,
+}
+
+/// Posts choice prompts for the agent and feeds the owner's choice back into the message queue.
+/// Each prompt can be answered once, after which its components are removed.
+pub struct Clarifications {
+    http: Arc<Http>,
+    channel: GuildChannel,
+    owner_id: UserId,
+    pending: Mutex<HashMap<MessageId, ChoicePrompt>>,
+}
+
+impl TypeMapKey for Clarifications {
+    type Value = Arc<Clarifications>;
+}
+
+impl Clarifications {
+    pub fn new(http: Arc<Http>, channel: GuildChannel, owner_id: UserId) -> Self {
+        Self {
+            http,
+            channel,
+            owner_id,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn ask(
+        &self,
+        question: String,
+        options: Vec<ChoiceOption>,
+        style: ChoiceStyle
+    ) -> serenity::Result<Message> {
+        let components = match style {
+            ChoiceStyle::Buttons => options
+                .chunks(BUTTONS_PER_ROW)
+                .enumerate()
+                .map(|(row, chunk)| CreateActionRow::Buttons(chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(i, option)| CreateButton::new(format!("{CLARIFY_ID_PREFIX}{}", row * BUTTONS_PER_ROW + i))
+                        .label(option.label.clone())
+                        .style(ButtonStyle::Primary))
+                    .collect()))
+                .collect(),
+            ChoiceStyle::Select => vec![CreateActionRow::SelectMenu(CreateSelectMenu::new(
+                CLARIFY_SELECT_ID,
+                CreateSelectMenuKind::String {
+                    options: options
+                        .iter()
+                        .map(|option| {
+                            let menu_option = CreateSelectMenuOption::new(option.label.clone(), option.value.clone());
+                            match &option.description {
+                                Some(description) => menu_option.description(description.clone()),
+                                None => menu_option,
+                            }
+                        })
+                        .collect()
+                })
+                .placeholder("Choose an option"))],
+        };
+
+        let embed = CreateEmbed::new()
+            .title("❓ Question")
+            .description(question.clone());
+        let message = CreateMessage::new()
+            .embed(embed)
+            .components(components);
+
+        let msg = self.channel.send_message(&self.http, message).await?;
+        self.pending.lock().await.insert(msg.id, ChoicePrompt {
+            question,
+            options,
+        });
+
+        Ok(msg)
+    }
+
+    /// Handles a choice made on a prompt.  Returns false if the interaction was not for a prompt.
+    pub async fn handle_interaction(
+        &self,
+        ctx: &Context,
+        interaction: &ComponentInteraction,
+        watcher: &ThreadWatcher
+    ) -> bool {
+        if !interaction.data.custom_id.starts_with(CLARIFY_ID_PREFIX) {
+            return false;
+        }
+
+        if interaction.user.id != self.owner_id {
+            let response = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                .content("Only the owner of this thread can answer this question")
+                .ephemeral(true));
+            if let Err(e) = interaction.create_response(&ctx.http, response).await {
+                warn!("Could not respond to choice interaction: {e}");
+            }
+
+            return true;
+        }
+
+        let mut pending = self.pending.lock().await;
+        let Some(prompt) = pending.get(&interaction.message.id) else {
+            if let Err(e) = interaction.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await {
+                warn!("Could not respond to choice interaction: {e}");
+            }
+
+            return true;
+        };
+
+        let option = match &interaction.data.kind {
+            ComponentInteractionDataKind::Button => interaction.data.custom_id
+                .trim_start_matches(CLARIFY_ID_PREFIX)
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| prompt.options.get(i)),
+            ComponentInteractionDataKind::StringSelect { values } => values
+                .first()
+                .and_then(|value| prompt.options.iter().find(|option| &option.value == value)),
+            _ => None,
+        };
+
+        let Some(option) = option else {
+            warn!("Choice interaction \"{}\" did not match any option", interaction.data.custom_id);
+            if let Err(e) = interaction.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await {
+                warn!("Could not respond to choice interaction: {e}");
+            }
+
+            return true;
+        };
+
+        let response = CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+            .embed(CreateEmbed::new()
+                .title("❓ Question")
+                .description(format!("{}\n\n**{}**", prompt.question, option.label)))
+            .components(vec![]));
+        if let Err(e) = interaction.create_response(&ctx.http, response).await {
+            warn!("Could not respond to choice interaction: {e}");
+        }
+
+        let message = ThreadMessage {
+            id: interaction.message.id,
+            sender: interaction.user.id,
+            content: String::new(),
+            choice: Some(ThreadChoice {
+                question: prompt.question.clone(),
+                label: option.label.clone(),
+                value: option.value.clone(),
+            }),
+        };
+
+        pending.remove(&interaction.message.id);
+        if let Err(e) = watcher.queue(message).await {
+            error!("Could not send choice to MPSC channel: {e}");
+        }
+
+        true
+    }
+}
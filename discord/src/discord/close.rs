@@ -0,0 +1,232 @@
+use std::sync::Arc;
+use serenity::all::{ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage, EditThread, GuildChannel, Http, Message, MessageId, UserId};
+use serenity::prelude::TypeMapKey;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+use crate::discord::forum_tags::ForumTags;
+use crate::discord::shutdown::{Shutdown, ShutdownReason};
+use crate::discord::survey::{CloseReason, Survey};
+
+pub const CLOSE_CONFIRM_ID: &str = "close_thread:confirm";
+pub const CLOSE_CANCEL_ID: &str = "close_thread:cancel";
+
+/// A request from the agent to close the thread, waiting for confirmation from the thread owner
+pub struct CloseRequest {
+    pub summary: String,
+    pub lock: bool,
+}
+
+/// A close request along with the confirmation message posted for it
+struct PendingClose {
+    message_id: MessageId,
+    request: CloseRequest,
+}
+
+/// How the thread is left once it has been closed
+pub struct CloseOptions {
+    /// The forum tag applied when the agent closes the thread
+    pub resolved_tag: Option<String>,
+
+    /// Whether the thread is locked when it times out
+    pub timeout_lock: bool,
+
+    /// The forum tag applied when the thread times out
+    pub timeout_tag: Option<String>,
+}
+
+/// Closes the thread on behalf of the agent or after it has timed out.  The agent can only request
+/// that the thread is closed, the thread is closed when the owner presses the confirmation button
+/// posted with the request.
+pub struct ThreadCloser {
+    http: Arc<Http>,
+    channel: GuildChannel,
+    owner_id: UserId,
+    shutdown: Arc<Shutdown>,
+    forum_tags: Arc<ForumTags>,
+    survey: Arc<Survey>,
+    options: CloseOptions,
+    pending: Mutex<Option<PendingClose>>,
+}
+
+impl TypeMapKey for ThreadCloser {
+    type Value = Arc<ThreadCloser>;
+}
+
+impl ThreadCloser {
+    pub fn new(
+        http: Arc<Http>,
+        channel: GuildChannel,
+        owner_id: UserId,
+        shutdown: Arc<Shutdown>,
+        forum_tags: Arc<ForumTags>,
+        survey: Arc<Survey>,
+        options: CloseOptions,
+    ) -> Self {
+        Self {
+            http,
+            channel,
+            owner_id,
+            shutdown,
+            forum_tags,
+            survey,
+            options,
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// Posts a message asking the owner to confirm that the thread can be closed.  Any previous
+    /// request that was not confirmed is replaced, and the buttons are removed from its message.
+    pub async fn request(&self, request: CloseRequest) -> serenity::Result<Message> {
+        let embed = CreateEmbed::new()
+            .title("✅ Is your question resolved?")
+            .description(format!("<@{}>, if everything is sorted, this thread can be closed", self.owner_id));
+        let buttons = CreateActionRow::Buttons(vec![
+            CreateButton::new(CLOSE_CONFIRM_ID)
+                .label("Close thread")
+                .style(ButtonStyle::Success),
+            CreateButton::new(CLOSE_CANCEL_ID)
+                .label("Keep open")
+                .style(ButtonStyle::Secondary),
+        ]);
+        let message = CreateMessage::new()
+            .embed(embed)
+            .components(vec![buttons]);
+
+        let msg = self.channel.send_message(&self.http, message).await?;
+        let previous = self.pending.lock().await.replace(PendingClose {
+            message_id: msg.id,
+            request,
+        });
+
+        if let Some(previous) = previous {
+            let edit = EditMessage::new()
+                .embed(CreateEmbed::new()
+                    .title("✅ Is your question resolved?")
+                    .description("This request was replaced by a newer one below"))
+                .components(vec![]);
+            if let Err(e) = self.channel.id.edit_message(&self.http, previous.message_id, edit).await {
+                warn!("Could not remove the buttons from the previous close request: {e}");
+            }
+        }
+
+        Ok(msg)
+    }
+
+    /// Handles a button press on a close confirmation message.  Returns false if the interaction
+    /// was not for this closer.
+    pub async fn handle_interaction(&self, ctx: &Context, interaction: &ComponentInteraction) -> bool {
+        let custom_id = interaction.data.custom_id.as_str();
+        if custom_id != CLOSE_CONFIRM_ID && custom_id != CLOSE_CANCEL_ID {
+            return false;
+        }
+
+        // A confirmation message only closes the thread with the summary it was posted for
+        let is_owner = interaction.user.id == self.owner_id;
+        let request = if is_owner {
+            self.pending.lock().await
+                .take_if(|pending| pending.message_id == interaction.message.id)
+                .map(|pending| pending.request)
+        }
+        else {
+            None
+        };
+
+        let response = if !is_owner {
+            CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                .content("Only the owner of this thread can close it")
+                .ephemeral(true))
+        }
+        else if request.is_none() {
+            CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+                .embed(CreateEmbed::new()
+                    .title("✅ Is your question resolved?")
+                    .description("This request is no longer open"))
+                .components(vec![]))
+        }
+        else {
+            let description = if custom_id == CLOSE_CONFIRM_ID {
+                "Closing this thread"
+            }
+            else {
+                "This thread will stay open"
+            };
+
+            CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+                .embed(CreateEmbed::new()
+                    .title("✅ Is your question resolved?")
+                    .description(description))
+                .components(vec![]))
+        };
+
+        if let Err(e) = interaction.create_response(&ctx.http, response).await {
+            warn!("Could not respond to close interaction: {e}");
+        }
+
+        if is_owner {
+            match request {
+                Some(request) if custom_id == CLOSE_CONFIRM_ID => self.close(request).await,
+                Some(_) => info!("Thread owner chose to keep the thread open"),
+                None => warn!("Close interaction received for a close request that is no longer pending"),
+            }
+        }
+
+        true
+    }
+
+    /// Posts the resolution summary, runs the survey, applies the resolved tag (if configured),
+    /// archives the thread and shuts down
+    async fn close(&self, request: CloseRequest) {
+        if !self.shutdown.begin(ShutdownReason::Resolved) {
+            return;
+        }
+
+        let embed = CreateEmbed::new()
+            .title("✅ Resolved")
+            .description(request.summary);
+        if let Err(e) = self.channel.send_message(&self.http, CreateMessage::new().embed(embed)).await {
+            error!("Error sending resolution summary: {e}");
+        }
+
+        self.survey.run(CloseReason::Agent).await;
+        self.archive(request.lock, self.options.resolved_tag.as_deref()).await;
+
+        warn!("Thread has been resolved, shutting down {} ({})",
+            self.channel.name, self.channel.id);
+
+        self.shutdown.finish().await;
+    }
+
+    /// Runs the survey, applies the timeout tag (if configured), archives the thread and shuts
+    /// down.  The timeout message is expected to have been posted already.
+    pub async fn time_out(&self) {
+        if !self.shutdown.begin(ShutdownReason::Timeout) {
+            return;
+        }
+
+        self.survey.run(CloseReason::Timeout).await;
+        self.archive(self.options.timeout_lock, self.options.timeout_tag.as_deref()).await;
+
+        warn!("Thread has timed out, shutting down {} ({})",
+            self.channel.name, self.channel.id);
+
+        self.shutdown.finish().await;
+    }
+
+    async fn archive(&self, lock: bool, tag: Option<&str>) {
+        let mut edit = EditThread::new()
+            .archived(true)
+            .locked(lock);
+
+        if let Some(tag) = tag {
+            match self.forum_tags.applied_with(tag).await {
+                Ok(Some(tags)) => edit = edit.applied_tags(tags),
+                Ok(None) => warn!("Forum tag \"{tag}\" does not exist"),
+                Err(e) => warn!("Could not get forum tags: {e}"),
+            }
+        }
+
+        if let Err(e) = self.channel.id.edit_thread(&self.http, edit).await {
+            error!("Error archiving thread: {e}");
+        }
+    }
+}
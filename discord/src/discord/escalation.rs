@@ -0,0 +1,285 @@
+use std::sync::Arc;
+use serenity::all::{ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateAllowedMentions, CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, GuildChannel, Http, Message, RoleId, UserId};
+use serenity::prelude::TypeMapKey;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use tracing::{info, warn};
+use crate::discord::forum_tags::ForumTags;
+use crate::timeout::Timeout;
+use crate::timeout::machine::Clock;
+use crate::timeout::sink::TimeoutSink;
+
+pub const HAND_BACK_ID: &str = "escalation:hand_back";
+
+/// Hands the thread over to human staff.  While escalated, messages are still queued but the
+/// agent does not process them until a staff member responds in the thread or hands the thread
+/// back with the button posted in the thread.
+///
+/// Staff are members with the staff role.  Without a staff role, replies in the thread cannot be
+/// told apart from anyone else's, so the thread is only handed back with the button, by a member
+/// who can manage threads.
+pub struct Escalation {
+    http: Arc<Http>,
+    channel: GuildChannel,
+    owner_id: UserId,
+    staff_role: Option<RoleId>,
+    staff_channel: Option<ChannelId>,
+    staff_tag: Option<String>,
+    forum_tags: Arc<ForumTags>,
+    paused: watch::Sender<bool>,
+}
+
+impl TypeMapKey for Escalation {
+    type Value = Arc<Escalation>;
+}
+
+impl Escalation {
+    pub fn new(
+        http: Arc<Http>,
+        channel: GuildChannel,
+        owner_id: UserId,
+        staff_role: Option<RoleId>,
+        staff_channel: Option<ChannelId>,
+        staff_tag: Option<String>,
+        forum_tags: Arc<ForumTags>,
+    ) -> Self {
+        Self {
+            http,
+            channel,
+            owner_id,
+            staff_role,
+            staff_channel,
+            staff_tag,
+            forum_tags,
+            paused: watch::Sender::new(false),
+        }
+    }
+
+    /// Whether there is anywhere to send an escalation to
+    pub fn is_configured(&self) -> bool {
+        self.staff_role.is_some() || self.staff_channel.is_some()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Waits until the agent is allowed to process messages again.  Returns immediately if the
+    /// thread is not escalated.
+    pub async fn wait_until_resumed(&self) {
+        let mut paused = self.paused.subscribe();
+        let _ = paused.wait_for(|paused| !paused).await;
+    }
+
+    /// Waits until the agent can take its next turn, restarting the inactivity timeout paused for
+    /// the last turn.  The timeout stays paused while the thread is escalated so that it cannot
+    /// close a thread staff were asked to look at, and runs from when it was handed back.
+    pub async fn wait_for_turn<S: TimeoutSink, C: Clock + Clone>(&self, timeout: &Timeout<S, C>, last_sent: Option<Instant>) {
+        if self.is_paused() {
+            self.wait_until_resumed().await;
+            timeout.resume(None);
+        }
+        else {
+            timeout.resume(last_sent);
+        }
+    }
+
+    pub fn is_staff(&self, roles: &[RoleId]) -> bool {
+        self.staff_role.is_some_and(|role| roles.contains(&role))
+    }
+
+    /// Called for every message posted in the thread, resuming the agent if a staff member has
+    /// responded
+    pub fn on_message(&self, message: &Message) {
+        if !self.is_paused() {
+            return;
+        }
+
+        let roles = message.member
+            .as_ref()
+            .map(|member| member.roles.as_slice())
+            .unwrap_or_default();
+
+        if self.is_staff(roles) {
+            info!("Staff member {} responded, resuming automatic replies", message.author.id);
+            self.paused.send_replace(false);
+        }
+    }
+
+    /// Notifies staff, applies the staff tag and tells the owner that a human is on the way.
+    /// Automatic replies are paused once this returns.
+    pub async fn escalate(&self, summary: String) -> serenity::Result<Message> {
+        let link = format!("https://discord.com/channels/{}/{}", self.channel.guild_id, self.channel.id);
+        let staff_embed = CreateEmbed::new()
+            .title("🆘 Support thread needs staff")
+            .url(link.clone())
+            .description(format!("**{}**\n{summary}\n\n{link}", self.channel.name));
+
+        let mut staff_message = CreateMessage::new()
+            .embed(staff_embed);
+        if let Some(role) = self.staff_role {
+            staff_message = staff_message
+                .content(format!("<@&{role}>"))
+                .allowed_mentions(CreateAllowedMentions::new().roles(vec![role]));
+        }
+
+        // Staff are notified in the staff channel if there is one, otherwise in the thread
+        match self.staff_channel {
+            Some(staff_channel) => {
+                staff_channel.send_message(&self.http, staff_message).await?;
+            }
+            None => {
+                self.channel.send_message(&self.http, staff_message).await?;
+            }
+        }
+
+        if let Some(staff_tag) = &self.staff_tag {
+            match self.forum_tags.applied_with(staff_tag).await {
+                Ok(Some(tags)) => {
+                    if let Err(e) = self.forum_tags.set_applied(tags).await {
+                        warn!("Could not apply forum tag \"{staff_tag}\": {e}");
+                    }
+                }
+                Ok(None) => warn!("Forum tag \"{staff_tag}\" does not exist"),
+                Err(e) => warn!("Could not get forum tags: {e}"),
+            }
+        }
+
+        let embed = CreateEmbed::new()
+            .title("🙋 A human is on the way")
+            .description(format!("<@{}>, a member of staff has been asked to take a look.  Automatic \
+                replies are paused until they respond.", self.owner_id));
+        let button = CreateActionRow::Buttons(vec![
+            CreateButton::new(HAND_BACK_ID)
+                .label("Hand back to the bot")
+                .style(ButtonStyle::Secondary)
+        ]);
+        let msg = self.channel
+            .send_message(&self.http, CreateMessage::new().embed(embed).components(vec![button]))
+            .await?;
+
+        info!("Thread escalated to staff, pausing automatic replies");
+        self.paused.send_replace(true);
+
+        Ok(msg)
+    }
+
+    /// Handles the hand back button.  Returns false if the interaction was not for escalation.
+    pub async fn handle_interaction(&self, ctx: &Context, interaction: &ComponentInteraction) -> bool {
+        if interaction.data.custom_id != HAND_BACK_ID {
+            return false;
+        }
+
+        let is_staff = interaction.member
+            .as_ref()
+            .is_some_and(|member| self.is_staff(&member.roles)
+                || member.permissions.is_some_and(|permissions| permissions.manage_threads()));
+        let response = if is_staff {
+            CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+                .embed(CreateEmbed::new()
+                    .title("🙋 A human is on the way")
+                    .description(format!("<@{}> handed this thread back to the bot", interaction.user.id)))
+                .components(vec![]))
+        }
+        else {
+            CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                .content("Only staff can hand this thread back to the bot")
+                .ephemeral(true))
+        };
+
+        if let Err(e) = interaction.create_response(&ctx.http, response).await {
+            warn!("Could not respond to hand back interaction: {e}");
+        }
+
+        if is_staff {
+            info!("Staff member {} handed the thread back, resuming automatic replies", interaction.user.id);
+            self.paused.send_replace(false);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::time::sleep;
+    use crate::timeout::machine::{TimeoutEvent, TokioClock};
+
+    #[derive(Clone, Default)]
+    struct RecordingSink {
+        events: Arc<Mutex<Vec<TimeoutEvent>>>,
+    }
+
+    impl TimeoutSink for RecordingSink {
+        async fn send(&self, event: TimeoutEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn escalation() -> Escalation {
+        let http = Arc::new(Http::new(""));
+        let forum_tags = Arc::new(ForumTags::new(http.clone(), ChannelId::new(1), None, Vec::new()));
+        Escalation::new(http, GuildChannel::default(), UserId::new(2), None, None, None, forum_tags)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn escalated_thread_does_not_time_out() {
+        let sink = RecordingSink::default();
+        let timeout = Arc::new(Timeout::with_sink(sink.clone(), TokioClock, vec![minutes(30), minutes(60)]));
+        let escalation = Arc::new(escalation());
+        tokio::spawn({
+            let timeout = timeout.clone();
+            async move { timeout.run().await }
+        });
+
+        // The agent escalates during a turn, while the timeout is paused
+        timeout.pause();
+        escalation.paused.send_replace(true);
+        let turn = tokio::spawn({
+            let timeout = timeout.clone();
+            let escalation = escalation.clone();
+            async move { escalation.wait_for_turn(&timeout, None).await }
+        });
+
+        sleep(minutes(120)).await;
+        assert!(sink.events.lock().unwrap().is_empty());
+        assert!(!turn.is_finished());
+
+        // Staff hand the thread back, the clock starts again from then
+        escalation.paused.send_replace(false);
+        turn.await.unwrap();
+        sleep(minutes(59)).await;
+        assert_eq!(sink.events.lock().unwrap().len(), 1);
+        sleep(minutes(2)).await;
+        assert_eq!(sink.events.lock().unwrap().last(), Some(&TimeoutEvent::Expired { stage: 1 }));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout_resumes_from_the_last_message_when_not_escalated() {
+        let sink = RecordingSink::default();
+        let timeout = Arc::new(Timeout::with_sink(sink.clone(), TokioClock, vec![minutes(30), minutes(60)]));
+        let escalation = escalation();
+        tokio::spawn({
+            let timeout = timeout.clone();
+            async move { timeout.run().await }
+        });
+
+        timeout.pause();
+        sleep(minutes(1)).await;
+        let last_sent = Instant::now();
+        sleep(minutes(4)).await;
+        escalation.wait_for_turn(&timeout, Some(last_sent)).await;
+
+        sleep(minutes(25)).await;
+        assert!(sink.events.lock().unwrap().is_empty());
+        sleep(minutes(2)).await;
+        assert_eq!(sink.events.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use serenity::all::{ChannelId, EditThread, ForumTag, ForumTagId, Http};
+
+// https://discord.com/developers/docs/resources/channel#channel-object-channel-structure
+pub const APPLIED_TAG_LIMIT: usize = 5;
+
+/// Looks up forum tags for the thread being served.  Tags are always fetched from Discord instead
+/// of being cached, because staff may change tags while the thread is open.
+///
+/// The agent may only apply or remove tags named in the allowlist, tags applied by the
+/// application itself (such as the resolved tag) are not restricted.
+pub struct ForumTags {
+    http: Arc<Http>,
+    thread_id: ChannelId,
+    forum_id: Option<ChannelId>,
+    allowlist: Vec<String>,
+}
+
+impl ForumTags {
+    pub fn new(
+        http: Arc<Http>,
+        thread_id: ChannelId,
+        forum_id: Option<ChannelId>,
+        allowlist: Vec<String>,
+    ) -> Self {
+        Self {
+            http,
+            thread_id,
+            forum_id,
+            allowlist,
+        }
+    }
+
+    /// Whether the agent is allowed to apply or remove the named tag
+    pub fn is_allowed(&self, name: &str) -> bool {
+        self.allowlist
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(name))
+    }
+
+    /// Replaces the tags applied to the thread
+    pub async fn set_applied(&self, tags: Vec<ForumTagId>) -> serenity::Result<()> {
+        self.thread_id
+            .edit_thread(&self.http, EditThread::new().applied_tags(tags))
+            .await
+            .map(|_| ())
+    }
+
+    /// The tags that can be applied to threads in the parent forum.  This is empty if the thread is
+    /// not in a forum channel
+    pub async fn available(&self) -> serenity::Result<Vec<ForumTag>> {
+        let Some(forum_id) = self.forum_id else {
+            return Ok(Vec::new());
+        };
+
+        Ok(self.http.get_channel(forum_id).await?
+            .guild()
+            .map(|forum| forum.available_tags)
+            .unwrap_or_default())
+    }
+
+    /// The tags currently applied to the thread
+    pub async fn applied(&self) -> serenity::Result<Vec<ForumTagId>> {
+        Ok(self.http.get_channel(self.thread_id).await?
+            .guild()
+            .map(|thread| thread.applied_tags)
+            .unwrap_or_default())
+    }
+
+    /// Finds an available tag by name, ignoring case
+    pub async fn find(&self, name: &str) -> serenity::Result<Option<ForumTag>> {
+        Ok(self.available().await?
+            .into_iter()
+            .find(|tag| tag.name.eq_ignore_ascii_case(name)))
+    }
+
+    /// The currently applied tags with the named tag added, if it exists in the parent forum.  If
+    /// the tag does not exist, `None` is returned.  The oldest applied tags are dropped if adding
+    /// the tag would exceed [`APPLIED_TAG_LIMIT`].
+    pub async fn applied_with(&self, name: &str) -> serenity::Result<Option<Vec<ForumTagId>>> {
+        let Some(tag) = self.find(name).await? else {
+            return Ok(None);
+        };
+
+        let mut applied = self.applied().await?;
+        if !applied.contains(&tag.id) {
+            while applied.len() >= APPLIED_TAG_LIMIT {
+                applied.remove(0);
+            }
+
+            applied.push(tag.id);
+        }
+
+        Ok(Some(applied))
+    }
+}
@@ -1,3 +1,14 @@
 pub mod thread_watcher;
 pub mod tools;
-pub mod thread_message;
\ No newline at end of file
+pub mod thread_message;
+pub mod sent_messages;
+pub mod progress;
+pub mod forum_tags;
+pub mod close;
+pub mod escalation;
+pub mod clarification;
+pub mod survey;
+pub mod thread_search;
+pub mod outbound_filter;
+pub mod shutdown;
+pub mod budget;
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use serenity::all::{ChannelId, Http, MessageId, ReactionType};
+use serenity::http::Typing;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+const QUEUED_REACTION: char = '👀';
+const WORKING_REACTION: char = '⏳';
+const ANSWERED_REACTION: char = '✅';
+
+/// Shows the user that the agent is working on their messages.  Messages are reacted to with
+/// [`QUEUED_REACTION`] when they arrive, [`WORKING_REACTION`] once the agent loop picks them up
+/// (which is when other agents are consulted) and [`ANSWERED_REACTION`] when the agent loop has
+/// finished the turn.  The typing indicator is kept alive for the entire turn.
+pub struct Progress {
+    http: Arc<Http>,
+    channel_id: ChannelId,
+    turn: Mutex<Option<Turn>>
+}
+
+struct Turn {
+    messages: Vec<MessageId>,
+    typing: Typing
+}
+
+impl Progress {
+    pub fn new(http: Arc<Http>, channel_id: ChannelId) -> Self {
+        Self {
+            http,
+            channel_id,
+            turn: Mutex::new(None)
+        }
+    }
+
+    async fn react(&self, message: MessageId, reaction: char) {
+        if let Err(e) = self.channel_id.create_reaction(&self.http, message, reaction).await {
+            warn!("Could not add {reaction} reaction to message {message}: {e}");
+        }
+    }
+
+    async fn unreact(&self, message: MessageId, reaction: char) {
+        let reaction = ReactionType::from(reaction);
+        if let Err(e) = self.channel_id.delete_reaction(&self.http, message, None, reaction.clone()).await {
+            warn!("Could not remove {reaction} reaction from message {message}: {e}");
+        }
+    }
+
+    /// Called when a message is sent to the agent's message queue
+    pub async fn queued(&self, message: MessageId) {
+        self.react(message, QUEUED_REACTION).await;
+    }
+
+    /// Called when the agent loop takes messages from the queue and starts a new turn.  If a
+    /// previous turn was never finished, it is finished first.
+    pub async fn start_turn(&self, messages: Vec<MessageId>) {
+        self.finish_turn().await;
+
+        for message in &messages {
+            self.unreact(*message, QUEUED_REACTION).await;
+            self.react(*message, WORKING_REACTION).await;
+        }
+
+        *self.turn.lock().await = Some(Turn {
+            messages,
+            typing: Typing::start(self.http.clone(), self.channel_id),
+        });
+    }
+
+    /// Called when the agent loop has finished processing the current turn
+    pub async fn finish_turn(&self) {
+        let Some(turn) = self.turn.lock().await.take() else {
+            return;
+        };
+
+        turn.typing.stop();
+        for message in turn.messages {
+            self.unreact(message, WORKING_REACTION).await;
+            self.react(message, ANSWERED_REACTION).await;
+        }
+    }
+}
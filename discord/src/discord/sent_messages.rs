@@ -0,0 +1,37 @@
+use std::collections::HashSet;
+use serenity::all::MessageId;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// A registry of every message sent by this agent's tools.  Tools that modify existing messages
+/// must check this registry first so that the agent can only ever touch its own messages, never a
+/// user's or another bot's message
+#[derive(Default)]
+pub struct SentMessages {
+    ids: Mutex<HashSet<MessageId>>,
+    last_sent: Mutex<Option<Instant>>,
+}
+
+impl SentMessages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn insert(&self, id: MessageId) {
+        self.ids.lock().await.insert(id);
+        *self.last_sent.lock().await = Some(Instant::now());
+    }
+
+    /// When the most recent message was sent
+    pub async fn last_sent(&self) -> Option<Instant> {
+        *self.last_sent.lock().await
+    }
+
+    pub async fn contains(&self, id: MessageId) -> bool {
+        self.ids.lock().await.contains(&id)
+    }
+
+    pub async fn remove(&self, id: MessageId) -> bool {
+        self.ids.lock().await.remove(&id)
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use serenity::all::ShardManager;
+use serenity::prelude::TypeMapKey;
+use tokio::sync::watch;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// The thread owner confirmed that the thread is resolved
+    Resolved,
+
+    /// Nobody posted in the thread before the timeout
+    Timeout,
+
+    /// The thread was archived or locked by someone else
+    Archived,
+
+    /// The thread was deleted
+    Deleted,
+
+    /// The agent loop ended on its own
+    AgentExited,
+
+    /// The Discord gateway stopped without being asked to
+    GatewayClosed,
+
+    /// Messages could no longer be passed to the agent loop
+    QueueClosed,
+
+    /// The thread went over its budget and the agent was stopped
+    BudgetExceeded,
+}
+
+/// The single exit path for this agent.  Every way a thread can end shuts the Discord gateway down
+/// first and then signals [`Shutdown::wait`] so that main stops the agent loop, which closes the
+/// connection to Coral.
+///
+/// Closing a thread takes a while (the survey, archiving, etc.), so shutting down is split into
+/// [`Shutdown::begin`] and [`Shutdown::finish`].  Only the first caller of begin gets to close the
+/// thread, which stops e.g. the archive event caused by the closer from closing it a second time.
+pub struct Shutdown {
+    shard_manager: Arc<ShardManager>,
+    reason: Mutex<Option<ShutdownReason>>,
+    finished: watch::Sender<Option<ShutdownReason>>,
+}
+
+impl TypeMapKey for Shutdown {
+    type Value = Arc<Shutdown>;
+}
+
+impl Shutdown {
+    pub fn new(shard_manager: Arc<ShardManager>) -> Self {
+        Self {
+            shard_manager,
+            reason: Mutex::new(None),
+            finished: watch::Sender::new(None),
+        }
+    }
+
+    /// Marks the agent as shutting down.  Returns false if it is already shutting down for another
+    /// reason, in which case the caller should leave the thread alone.
+    pub fn begin(&self, reason: ShutdownReason) -> bool {
+        let mut current = self.reason.lock().unwrap();
+        if current.is_some() {
+            return false;
+        }
+
+        warn!("Shutting down: {reason:?}");
+        *current = Some(reason);
+        true
+    }
+
+    /// Shuts down the Discord gateway and then signals the agent loop to stop
+    pub async fn finish(&self) {
+        let reason = self.reason.lock().unwrap().unwrap_or(ShutdownReason::AgentExited);
+        self.shard_manager.shutdown_all().await;
+        self.finished.send_replace(Some(reason));
+    }
+
+    /// Shuts down straight away, for when there is nothing to do to the thread first
+    pub async fn shutdown(&self, reason: ShutdownReason) {
+        if self.begin(reason) {
+            self.finish().await;
+        }
+    }
+
+    /// Waits until [`Shutdown::finish`] has been called, returning the reason for shutting down
+    pub async fn wait(&self) -> ShutdownReason {
+        let mut finished = self.finished.subscribe();
+        let reason = finished.wait_for(Option::is_some).await
+            .map(|reason| *reason);
+
+        // The sender is owned by self, so waiting cannot fail
+        reason.ok().flatten().unwrap_or(ShutdownReason::AgentExited)
+    }
+}
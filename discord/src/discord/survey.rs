@@ -0,0 +1,286 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use serde::Serialize;
+use serenity::all::{ActionRowComponent, ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateButton, CreateEmbed, CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateModal, EditMessage, GuildChannel, Http, InputTextStyle, MessageId, ModalInteraction, Timestamp, UserId};
+use serenity::prelude::TypeMapKey;
+use tokio::sync::{Mutex, Notify};
+use tracing::{error, info, warn};
+
+const SURVEY_ID_PREFIX: &str = "survey:";
+const RATING_ID_PREFIX: &str = "survey:rating:";
+const FEEDBACK_BUTTON_ID: &str = "survey:feedback";
+const FEEDBACK_MODAL_ID: &str = "survey:feedback_modal";
+const FEEDBACK_INPUT_ID: &str = "survey:feedback_input";
+
+// https://discord.com/developers/docs/interactions/message-components#text-input-object
+const FEEDBACK_LIMIT: u16 = 1000;
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseReason {
+    Timeout,
+    Agent,
+    Archived,
+}
+
+/// One line in the survey store
+#[derive(Serialize)]
+struct SurveyRecord {
+    thread_id: ChannelId,
+    session_id: Option<String>,
+    reason: CloseReason,
+    rating: Option<u8>,
+    feedback: Option<String>,
+    duration_secs: i64,
+    message_count: Option<u32>,
+    closed_at: Timestamp,
+}
+
+#[derive(Default)]
+struct SurveyState {
+    message: Option<MessageId>,
+    rating: Option<u8>,
+    feedback: Option<String>,
+}
+
+/// Asks the thread owner to rate the support they received when the thread closes.  Ratings are
+/// given with a row of buttons, after which optional written feedback can be given in a modal.
+///
+/// The survey is open for a configured duration, after which the response (or lack of one) is
+/// appended to the survey store as a JSON line.  A survey is only ever run once per thread, no
+/// matter how many ways the thread is closed.
+pub struct Survey {
+    http: Arc<Http>,
+    channel: GuildChannel,
+    owner_id: UserId,
+    session_id: Option<String>,
+    duration: Duration,
+    store: PathBuf,
+    started: AtomicBool,
+    state: Mutex<SurveyState>,
+    feedback_received: Notify,
+}
+
+impl TypeMapKey for Survey {
+    type Value = Arc<Survey>;
+}
+
+impl Survey {
+    pub fn new(
+        http: Arc<Http>,
+        channel: GuildChannel,
+        owner_id: UserId,
+        session_id: Option<String>,
+        duration: Duration,
+        store: PathBuf,
+    ) -> Self {
+        Self {
+            http,
+            channel,
+            owner_id,
+            session_id,
+            duration,
+            store,
+            started: AtomicBool::new(false),
+            state: Mutex::new(SurveyState::default()),
+            feedback_received: Notify::new(),
+        }
+    }
+
+    /// Posts the survey and waits for it to be answered or for the survey duration to pass, then
+    /// records the result.  Returns false without doing anything if the survey is disabled or has
+    /// already been run.
+    pub async fn run(&self, reason: CloseReason) -> bool {
+        if self.duration.is_zero() || self.started.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+
+        let buttons = (1..=5)
+            .map(|rating| CreateButton::new(format!("{RATING_ID_PREFIX}{rating}"))
+                .label(rating.to_string())
+                .emoji('⭐')
+                .style(ButtonStyle::Secondary))
+            .collect();
+        let embed = CreateEmbed::new()
+            .title("⭐ How did we do?")
+            .description(format!("<@{}>, please rate the help you received in this thread from 1 \
+                (not helpful) to 5 (very helpful)", self.owner_id));
+        let message = CreateMessage::new()
+            .embed(embed)
+            .components(vec![CreateActionRow::Buttons(buttons)]);
+
+        match self.channel.send_message(&self.http, message).await {
+            Ok(msg) => self.state.lock().await.message = Some(msg.id),
+            Err(e) => {
+                error!("Error sending survey: {e}");
+                return false;
+            }
+        }
+
+        info!("Survey posted, waiting {} for a response", humantime::format_duration(self.duration));
+        let _ = tokio::time::timeout(self.duration, self.feedback_received.notified()).await;
+
+        let state = self.state.lock().await;
+        if let Some(message) = state.message {
+            let embed = CreateEmbed::new()
+                .title("⭐ How did we do?")
+                .description(match state.rating {
+                    Some(rating) => format!("Thanks for your rating of {rating}/5!"),
+                    None => "This survey has closed".to_string(),
+                });
+            let edit = EditMessage::new()
+                .embed(embed)
+                .components(vec![]);
+            if let Err(e) = self.channel.id.edit_message(&self.http, message, edit).await {
+                warn!("Could not close survey: {e}");
+            }
+        }
+
+        self.record(reason, state.rating, state.feedback.clone()).await;
+        true
+    }
+
+    /// Appends the survey result to the survey store
+    async fn record(&self, reason: CloseReason, rating: Option<u8>, feedback: Option<String>) {
+        let message_count = match self.http.get_channel(self.channel.id).await {
+            Ok(channel) => channel.guild().and_then(|x| x.message_count),
+            Err(e) => {
+                warn!("Could not get thread message count: {e}");
+                None
+            }
+        };
+
+        let created = self.channel.thread_metadata
+            .and_then(|x| x.create_timestamp)
+            .unwrap_or_else(|| self.channel.id.created_at());
+        let closed_at = Timestamp::now();
+
+        let record = SurveyRecord {
+            thread_id: self.channel.id,
+            session_id: self.session_id.clone(),
+            reason,
+            rating,
+            feedback,
+            duration_secs: closed_at.unix_timestamp() - created.unix_timestamp(),
+            message_count,
+            closed_at,
+        };
+
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Could not serialize survey record: {e}");
+                return;
+            }
+        };
+
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.store)
+            .and_then(|mut file| writeln!(file, "{line}"));
+
+        match result {
+            Ok(()) => info!("Survey recorded to {}", self.store.display()),
+            Err(e) => error!("Could not write survey record to {}: {e}", self.store.display()),
+        }
+    }
+
+    async fn respond(&self, ctx: &Context, interaction: &ComponentInteraction, response: CreateInteractionResponse) {
+        if let Err(e) = interaction.create_response(&ctx.http, response).await {
+            warn!("Could not respond to survey interaction: {e}");
+        }
+    }
+
+    /// Handles the rating and feedback buttons.  Returns false if the interaction was not for the
+    /// survey.
+    pub async fn handle_interaction(&self, ctx: &Context, interaction: &ComponentInteraction) -> bool {
+        let custom_id = interaction.data.custom_id.as_str();
+        if !custom_id.starts_with(SURVEY_ID_PREFIX) {
+            return false;
+        }
+
+        if interaction.user.id != self.owner_id {
+            self.respond(ctx, interaction, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                .content("Only the owner of this thread can answer this survey")
+                .ephemeral(true))).await;
+            return true;
+        }
+
+        if custom_id == FEEDBACK_BUTTON_ID {
+            let input = CreateInputText::new(InputTextStyle::Paragraph, "What could we do better?", FEEDBACK_INPUT_ID)
+                .required(false)
+                .max_length(FEEDBACK_LIMIT);
+            let modal = CreateModal::new(FEEDBACK_MODAL_ID, "Feedback")
+                .components(vec![CreateActionRow::InputText(input)]);
+
+            self.respond(ctx, interaction, CreateInteractionResponse::Modal(modal)).await;
+            return true;
+        }
+
+        let Some(rating) = custom_id
+            .strip_prefix(RATING_ID_PREFIX)
+            .and_then(|x| x.parse::<u8>().ok())
+            .filter(|x| (1..=5).contains(x)) else {
+            warn!("Unknown survey interaction \"{custom_id}\"");
+            self.respond(ctx, interaction, CreateInteractionResponse::Acknowledge).await;
+            return true;
+        };
+
+        self.state.lock().await.rating = Some(rating);
+        info!("Thread owner rated this thread {rating}/5");
+
+        let button = CreateButton::new(FEEDBACK_BUTTON_ID)
+            .label("Leave feedback")
+            .style(ButtonStyle::Secondary);
+        self.respond(ctx, interaction, CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+            .embed(CreateEmbed::new()
+                .title("⭐ How did we do?")
+                .description(format!("Thanks for your rating of {rating}/5!  If you have a moment, \
+                    let us know what we could do better.")))
+            .components(vec![CreateActionRow::Buttons(vec![button])]))).await;
+
+        true
+    }
+
+    /// Handles the feedback modal.  Returns false if the interaction was not for the survey.
+    pub async fn handle_modal(&self, ctx: &Context, interaction: &ModalInteraction) -> bool {
+        if interaction.data.custom_id != FEEDBACK_MODAL_ID {
+            return false;
+        }
+
+        let feedback = interaction.data.components
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) if input.custom_id == FEEDBACK_INPUT_ID => input.value.clone(),
+                _ => None,
+            })
+            .filter(|x| !x.trim().is_empty());
+
+        let rating = {
+            let mut state = self.state.lock().await;
+            state.feedback = feedback;
+            state.rating
+        };
+
+        let response = CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+            .embed(CreateEmbed::new()
+                .title("⭐ How did we do?")
+                .description(match rating {
+                    Some(rating) => format!("Thanks for your rating of {rating}/5 and your feedback!"),
+                    None => "Thanks for your feedback!".to_string(),
+                }))
+            .components(vec![]));
+        if let Err(e) = interaction.create_response(&ctx.http, response).await {
+            warn!("Could not respond to survey feedback: {e}");
+        }
+
+        self.feedback_received.notify_one();
+        true
+    }
+}
@@ -1,22 +1,38 @@
 use serde::Serialize;
-use serenity::all::{Message, UserId};
+use serenity::all::{Message, MessageId, UserId};
 
 /// This thread message is given to AI models.  Currently, it only includes the message content but
 /// should be extended to include other fields from Discord that the user may populate.
 ///
 /// For example, the user may send GIFs, images or attachments that the AI models will ignore if not
 /// here
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ThreadMessage {
+    pub id: MessageId,
     pub sender: UserId,
-    pub content: String
+    pub content: String,
+
+    /// Set when this message is the user's answer to a choice prompt rather than a typed message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub choice: Option<ThreadChoice>
+}
+
+/// A choice made by the user with the buttons or select menu of a choice prompt.  The ID of the
+/// [`ThreadMessage`] carrying this is the ID of the prompt message.
+#[derive(Serialize, Clone)]
+pub struct ThreadChoice {
+    pub question: String,
+    pub label: String,
+    pub value: String
 }
 
 impl From<&Message> for ThreadMessage {
     fn from(message: &Message) -> ThreadMessage {
         ThreadMessage {
+            id: message.id,
             sender: message.author.id,
-            content: message.content.clone()
+            content: message.content.clone(),
+            choice: None
         }
     }
 }
@@ -24,8 +40,10 @@ impl From<&Message> for ThreadMessage {
 impl From<Message> for ThreadMessage {
     fn from(message: Message) -> ThreadMessage {
         ThreadMessage {
+            id: message.id,
             sender: message.author.id,
-            content: message.content
+            content: message.content,
+            choice: None
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use serenity::all::{ChannelId, GetMessages, GuildChannel, GuildId, Http};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use crate::discord::forum_tags::ForumTags;
+
+/// Discord returns at most this many archived threads per request
+const ARCHIVED_THREAD_LIMIT: u64 = 100;
+
+/// How long the index of archived threads is used before it is fetched again
+const INDEX_LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+/// The number of messages at the end of a thread searched for a resolution
+const RESOLUTION_SEARCH_DEPTH: u8 = 20;
+const SNIPPET_LENGTH: usize = 300;
+
+/// Title of the embed posted by [`crate::discord::close::ThreadCloser`]
+const RESOLUTION_EMBED_TITLE: &str = "✅ Resolved";
+
+pub struct SearchResult {
+    pub title: String,
+    pub link: String,
+    pub snippet: Option<String>,
+}
+
+struct IndexedThread {
+    id: ChannelId,
+    title: String,
+    tags: Vec<String>,
+    resolution: Option<String>,
+}
+
+struct Index {
+    built: Instant,
+    threads: Vec<IndexedThread>,
+}
+
+/// Searches previously resolved threads in the same forum.  The index is built from the forum's
+/// most recently archived threads that were closed with a resolution or carry the resolved tag, so
+/// threads that timed out or were archived unanswered are left out.  Threads are matched on their
+/// title, tags and resolution.  Resolutions are kept between index rebuilds, an archived thread's
+/// messages are only fetched once.
+pub struct ThreadSearch {
+    http: Arc<Http>,
+    guild_id: GuildId,
+    thread_id: ChannelId,
+    forum_id: Option<ChannelId>,
+    forum_tags: Arc<ForumTags>,
+    resolved_tag: Option<String>,
+    index: Mutex<Option<Index>>,
+    resolutions: Mutex<HashMap<ChannelId, Option<String>>>,
+}
+
+impl ThreadSearch {
+    pub fn new(
+        http: Arc<Http>,
+        channel: &GuildChannel,
+        forum_tags: Arc<ForumTags>,
+        resolved_tag: Option<String>,
+    ) -> Self {
+        Self {
+            http,
+            guild_id: channel.guild_id,
+            thread_id: channel.id,
+            forum_id: channel.parent_id,
+            forum_tags,
+            resolved_tag,
+            index: Mutex::new(None),
+            resolutions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn build_index(&self) -> serenity::Result<Vec<IndexedThread>> {
+        let Some(forum_id) = self.forum_id else {
+            return Ok(Vec::new());
+        };
+
+        let tags = self.forum_tags.available().await?;
+        let tag_name = |id| tags
+            .iter()
+            .find(|tag| tag.id == id)
+            .map(|tag| tag.name.clone());
+
+        let archived = forum_id
+            .get_archived_public_threads(&self.http, None, Some(ARCHIVED_THREAD_LIMIT)).await?
+            .threads;
+
+        let mut threads = Vec::new();
+        for thread in archived.into_iter().filter(|thread| thread.id != self.thread_id) {
+            let tags = thread.applied_tags
+                .into_iter()
+                .filter_map(tag_name)
+                .collect::<Vec<_>>();
+            let has_resolved_tag = self.resolved_tag
+                .as_ref()
+                .is_some_and(|resolved| tags.iter().any(|tag| tag.eq_ignore_ascii_case(resolved)));
+
+            let resolution = self.resolution(thread.id).await;
+            if resolution.is_some() || has_resolved_tag {
+                threads.push(IndexedThread {
+                    id: thread.id,
+                    title: thread.name,
+                    tags,
+                    resolution,
+                });
+            }
+        }
+
+        info!("Indexed {} resolved threads", threads.len());
+        Ok(threads)
+    }
+
+    /// Finds the resolution summary posted when a thread was closed by
+    /// [`crate::discord::close::ThreadCloser`]
+    async fn resolution(&self, thread_id: ChannelId) -> Option<String> {
+        if let Some(resolution) = self.resolutions.lock().await.get(&thread_id) {
+            return resolution.clone();
+        }
+
+        let messages = match thread_id
+            .messages(&self.http, GetMessages::new().limit(RESOLUTION_SEARCH_DEPTH)).await {
+            Ok(messages) => messages,
+            Err(e) => {
+                // Not cached, so the thread is tried again when the index is next rebuilt
+                warn!("Could not get messages for thread {thread_id}: {e}");
+                return None;
+            }
+        };
+
+        let resolution = messages
+            .iter()
+            .flat_map(|message| message.embeds.iter())
+            .find(|embed| embed.title.as_deref() == Some(RESOLUTION_EMBED_TITLE))
+            .and_then(|embed| embed.description.clone());
+
+        self.resolutions.lock().await.insert(thread_id, resolution.clone());
+        resolution
+    }
+
+    /// Returns up to `limit` threads matching the most keywords from the query, best match first
+    pub async fn search(&self, query: &str, limit: usize) -> serenity::Result<Vec<SearchResult>> {
+        let keywords = query
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.len() > 2)
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<_>>();
+
+        let mut index = self.index.lock().await;
+        if index.as_ref().is_none_or(|index| index.built.elapsed() > INDEX_LIFETIME) {
+            *index = Some(Index {
+                built: Instant::now(),
+                threads: self.build_index().await?,
+            });
+        }
+
+        let mut matches = index
+            .iter()
+            .flat_map(|index| index.threads.iter())
+            .map(|thread| {
+                let haystack = format!("{} {} {}",
+                    thread.title,
+                    thread.tags.join(" "),
+                    thread.resolution.as_deref().unwrap_or_default()).to_lowercase();
+                let score = keywords
+                    .iter()
+                    .filter(|keyword| haystack.contains(keyword.as_str()))
+                    .count();
+
+                (score, thread)
+            })
+            .filter(|(score, _)| *score > 0)
+            .collect::<Vec<_>>();
+
+        // The archived thread list is most recently archived first, a stable sort keeps newer
+        // threads first when scores are equal
+        matches.sort_by_key(|(score, _)| Reverse(*score));
+
+        let mut results = Vec::new();
+        for (_, thread) in matches.into_iter().take(limit) {
+            results.push(SearchResult {
+                title: thread.title.clone(),
+                link: format!("https://discord.com/channels/{}/{}", self.guild_id, thread.id),
+                snippet: thread.resolution.as_deref().map(snippet),
+            });
+        }
+
+        Ok(results)
+    }
+}
+
+fn snippet(resolution: &str) -> String {
+    if resolution.chars().count() > SNIPPET_LENGTH {
+        format!("{}…", resolution.chars().take(SNIPPET_LENGTH).collect::<String>())
+    }
+    else {
+        resolution.to_string()
+    }
+}
@@ -1,20 +1,30 @@
-use serenity::all::{Context, GuildChannel, PartialGuildChannel, Ready, ShardManager};
+use serenity::all::{Context, EditThread, GuildChannel, Interaction, PartialGuildChannel, Ready};
 use serenity::client::EventHandler;
 use serenity::{async_trait};
 use std::sync::Arc;
 use serenity::futures::StreamExt;
 use serenity::prelude::TypeMapKey;
+use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tokio::sync::{mpsc, Mutex};
 use tracing::{error, info, warn};
+use crate::discord::clarification::Clarifications;
+use crate::discord::close::ThreadCloser;
+use crate::discord::escalation::Escalation;
+use crate::discord::progress::Progress;
+use crate::discord::shutdown::{Shutdown, ShutdownReason};
+use crate::discord::survey::{CloseReason, Survey};
 use crate::discord::thread_message::ThreadMessage;
 use crate::timeout::Timeout;
+use crate::transcript::Transcript;
 
 pub struct ThreadWatcher {
     channel: GuildChannel,
     pub sender: Arc<Mutex<UnboundedSender<ThreadMessage>>>,
     pub receiver: Arc<Mutex<UnboundedReceiver<ThreadMessage>>>,
-    timeout: Arc<Timeout>
+    timeout: Arc<Timeout>,
+    progress: Arc<Progress>,
+    transcript: Arc<Transcript>
 }
 
 pub struct ThreadEventHandler;
@@ -26,8 +36,12 @@ impl EventHandler for ThreadEventHandler {
             .get::<ThreadWatcher>()
             .unwrap();
 
-        let shard_manager = data
-            .get::<ShardManagerContainer>()
+        let shutdown = data
+            .get::<Shutdown>()
+            .unwrap();
+
+        let escalation = data
+            .get::<Escalation>()
             .unwrap();
 
         info!("Listening for messages in channel {} ({})",
@@ -37,20 +51,72 @@ impl EventHandler for ThreadEventHandler {
             .stream();
 
         while let Some(message) = stream.next().await {
+            // The bot's own messages are only recorded, whether the agent or the bot itself posted them
             if message.author.id == ready.user.id {
+                watcher.transcript.record_bot_message(&message);
                 continue;
             }
 
-            let sender = watcher.sender.lock().await;
-            if let Err(e) = sender.send(message.into()) {
+            escalation.on_message(&message);
+
+            if let Err(e) = watcher.queue(message.into()).await {
                 error!("Could not send message from collector to MPSC channel: {e}");
-                shard_manager.shutdown_all().await;
+                shutdown.shutdown(ShutdownReason::QueueClosed).await;
             }
-            else {
-                if let Err(e) = watcher.timeout.reset().await {
-                    warn!("Timeout could not be reset!: {e}");
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let data = ctx.data.read().await;
+        let watcher = data
+            .get::<ThreadWatcher>()
+            .unwrap();
+
+        let closer = data
+            .get::<ThreadCloser>()
+            .unwrap();
+
+        let escalation = data
+            .get::<Escalation>()
+            .unwrap();
+
+        let clarifications = data
+            .get::<Clarifications>()
+            .unwrap();
+
+        let survey = data
+            .get::<Survey>()
+            .unwrap();
+
+        let timeout = data
+            .get::<Timeout>()
+            .unwrap();
+
+        // Every discord agent shares the same bot, so interactions from other threads are received
+        // here too and must be left for the agent responsible for that thread
+        match interaction {
+            Interaction::Component(component) if component.channel_id == watcher.channel.id => {
+                watcher.transcript.record_interaction(&component);
+
+                let handled = closer.handle_interaction(&ctx, &component).await
+                    || escalation.handle_interaction(&ctx, &component).await
+                    || clarifications.handle_interaction(&ctx, &component, watcher).await
+                    || survey.handle_interaction(&ctx, &component).await
+                    || timeout.handle_interaction(&ctx, &component).await;
+
+                if !handled {
+                    warn!("Unhandled interaction \"{}\" in {} ({})",
+                        component.data.custom_id, watcher.channel.name, watcher.channel.id);
+                }
+            }
+            Interaction::Modal(modal) if modal.channel_id == watcher.channel.id => {
+                let handled = survey.handle_modal(&ctx, &modal).await;
+                if !handled {
+                    warn!("Unhandled modal \"{}\" in {} ({})",
+                        modal.data.custom_id, watcher.channel.name, watcher.channel.id);
                 }
             }
+            _ => {}
         }
     }
 
@@ -65,17 +131,30 @@ impl EventHandler for ThreadEventHandler {
             .get::<ThreadWatcher>()
             .unwrap();
 
-        let shard_manager = data
-            .get::<ShardManagerContainer>()
+        let shutdown = data
+            .get::<Shutdown>()
             .unwrap();
 
-        if let Some(data) = new.thread_metadata {
-            if watcher.channel.id == new.id && (data.archived || data.locked) {
-                warn!("Thread has been archived or locked, shutting down {} ({})",
-                    watcher.channel.name, watcher.channel.id);
+        let survey = data
+            .get::<Survey>()
+            .unwrap();
 
-                shard_manager.shutdown_all().await;
+        // Closing the thread archives it, so this event is also received when the thread is closed
+        // by the agent or times out.  Shutdown has already begun in that case
+        if let Some(data) = new.thread_metadata
+            && watcher.channel.id == new.id && (data.archived || data.locked)
+            && shutdown.begin(ShutdownReason::Archived) {
+            // Posting the survey unarchives the thread, so it is archived again once the survey
+            // is over.  Nobody but staff can post in a locked thread, so there is no survey
+            if !data.locked && survey.run(CloseReason::Archived).await
+                && let Err(e) = new.id.edit_thread(&ctx.http, EditThread::new().archived(true)).await {
+                error!("Error archiving thread after survey: {e}");
             }
+
+            warn!("Thread has been archived or locked, shutting down {} ({})",
+                watcher.channel.name, watcher.channel.id);
+
+            shutdown.finish().await;
         }
     }
 
@@ -90,37 +169,54 @@ impl EventHandler for ThreadEventHandler {
             .get::<ThreadWatcher>()
             .unwrap();
 
-        let shard_manager = data
-            .get::<ShardManagerContainer>()
+        let shutdown = data
+            .get::<Shutdown>()
             .unwrap();
 
         if watcher.channel.id == thread.id {
             warn!("Thread {} ({}) has been deleted, shutting down",
                     watcher.channel.name, watcher.channel.id);
 
-            shard_manager.shutdown_all().await;
+            shutdown.shutdown(ShutdownReason::Deleted).await;
         }
     }
 }
 
-pub struct ShardManagerContainer;
-
-impl TypeMapKey for ShardManagerContainer {
-    type Value = Arc<ShardManager>;
-}
-
 impl TypeMapKey for ThreadWatcher {
     type Value = Arc<ThreadWatcher>;
 }
 
 impl ThreadWatcher {
-    pub fn new(channel: GuildChannel, timeout: Arc<Timeout>) -> Self {
+    pub fn new(
+        channel: GuildChannel,
+        timeout: Arc<Timeout>,
+        progress: Arc<Progress>,
+        transcript: Arc<Transcript>
+    ) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
         Self {
             channel,
             sender: Arc::new(tx.into()),
             receiver: Arc::new(rx.into()),
-            timeout
+            timeout,
+            progress,
+            transcript
+        }
+    }
+
+    /// Sends a message to the agent's message queue, resetting the timeout.  Choices carry the id
+    /// of the bot's question, which is not reacted to
+    pub async fn queue(&self, message: ThreadMessage) -> Result<(), SendError<ThreadMessage>> {
+        let id = message.id;
+        let is_choice = message.choice.is_some();
+        self.transcript.record_message(&message);
+        self.sender.lock().await.send(message)?;
+
+        self.timeout.reset();
+
+        if !is_choice {
+            self.progress.queued(id).await;
         }
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp::schemars::schema_for;
+use coral_rs::rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use crate::discord::clarification::{ChoiceOption, ChoiceStyle, Clarifications};
+use crate::discord::outbound_filter::OutboundFilter;
+use crate::discord::tools::{ResponseError, ThreadRespondToolOutput, ToolResponse};
+
+pub const ASK_CHOICE_TOOL_NAME: &str = "ask_discord_choice";
+
+// https://discord.com/developers/docs/interactions/message-components
+const BUTTON_COUNT_LIMIT: usize = 25;
+const BUTTON_LABEL_LIMIT: usize = 80;
+const SELECT_OPTION_COUNT_LIMIT: usize = 25;
+const SELECT_OPTION_LIMIT: usize = 100;
+const QUESTION_LIMIT: usize = 4096;
+
+pub struct AskChoiceTool {
+    clarifications: Arc<Clarifications>,
+    filter: Arc<OutboundFilter>
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Style {
+    #[schemars(description = "One button per option, best for a few short options")]
+    Buttons,
+
+    #[schemars(description = "A drop down menu, best for many options or options that need a description")]
+    Select,
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct Choice {
+    #[schemars(description = "The text shown to the user")]
+    label: String,
+
+    #[schemars(description = "The value returned to you when this option is chosen")]
+    value: String,
+
+    #[schemars(description = "Extra text shown below the label, only used by the select style")]
+    description: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct Args {
+    #[schemars(description = "The question to ask, markdown is supported")]
+    question: String,
+
+    #[schemars(description = "The options the user can choose from")]
+    options: Vec<Choice>,
+
+    #[schemars(description = "How the options are shown")]
+    style: Style,
+}
+
+impl AskChoiceTool {
+    pub fn new(clarifications: Arc<Clarifications>, filter: Arc<OutboundFilter>) -> Self {
+        Self {
+            clarifications,
+            filter
+        }
+    }
+}
+
+fn check_length(name: &str, value: &str, limit: usize) -> Result<(), String> {
+    let length = value.chars().count();
+    if value.trim().is_empty() || length > limit {
+        Err(format!("{name} must be between 1 and {limit} characters long, it is {length}"))
+    }
+    else {
+        Ok(())
+    }
+}
+
+impl Args {
+    fn validate(&self) -> Result<(), String> {
+        check_length("question", &self.question, QUESTION_LIMIT)?;
+
+        let (count_limit, label_limit) = match self.style {
+            Style::Buttons => (BUTTON_COUNT_LIMIT, BUTTON_LABEL_LIMIT),
+            Style::Select => (SELECT_OPTION_COUNT_LIMIT, SELECT_OPTION_LIMIT),
+        };
+
+        if self.options.len() < 2 || self.options.len() > count_limit {
+            return Err(format!("between 2 and {count_limit} options must be given, {} were given",
+                self.options.len()));
+        }
+
+        let mut values = HashSet::new();
+        for (i, option) in self.options.iter().enumerate() {
+            check_length(&format!("option {i} label"), &option.label, label_limit)?;
+            check_length(&format!("option {i} value"), &option.value, SELECT_OPTION_LIMIT)?;
+            if let Some(description) = &option.description {
+                check_length(&format!("option {i} description"), description, SELECT_OPTION_LIMIT)?;
+            }
+
+            if !values.insert(option.value.as_str()) {
+                return Err(format!("option {i} has the same value as another option, values must be unique"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Tool for AskChoiceTool {
+    const NAME: &'static str = ASK_CHOICE_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = Args;
+    type Output = ToolResponse<ThreadRespondToolOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(Args)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Asks the thread owner to pick one of several options with buttons or a \
+                select menu.  Their choice will arrive later as a new message with a choice field.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if let Err(e) = args.validate() {
+            return Ok(ToolResponse::rejected(e));
+        }
+
+        let warning = match self.filter.check(Self::NAME, std::iter::once(&mut args.question)
+            .chain(args.options.iter_mut().flat_map(|option| std::iter::once(&mut option.label)
+                .chain(option.description.as_mut())))) {
+            Ok(warning) => warning,
+            Err(e) => return Ok(ToolResponse::rejected(e)),
+        };
+
+        let style = match args.style {
+            Style::Buttons => ChoiceStyle::Buttons,
+            Style::Select => ChoiceStyle::Select,
+        };
+
+        let options = args.options
+            .into_iter()
+            .map(|option| ChoiceOption {
+                label: option.label,
+                value: option.value,
+                description: option.description,
+            })
+            .collect();
+
+        let msg = self.clarifications
+            .ask(args.question, options, style).await
+            .map_err(ResponseError::SerenityError)?;
+
+        Ok(ToolResponse::with_warning(ThreadRespondToolOutput {
+            id: msg.id,
+            timestamp: msg.timestamp
+        }, warning))
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp::schemars::schema_for;
+use coral_rs::rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use serenity::all::MessageId;
+use crate::discord::close::{CloseRequest, ThreadCloser};
+use crate::discord::outbound_filter::OutboundFilter;
+use crate::discord::tools::{ResponseError, ToolResponse};
+
+pub const CLOSE_THREAD_TOOL_NAME: &str = "close_thread";
+
+// https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+const SUMMARY_LIMIT: usize = 4096;
+
+pub struct CloseThreadTool {
+    closer: Arc<ThreadCloser>,
+    filter: Arc<OutboundFilter>
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct Args {
+    #[schemars(description = "A summary of the problem and how it was resolved, markdown is supported")]
+    summary: String,
+
+    #[schemars(description = "Whether the thread should also be locked so that nobody can reopen it")]
+    #[serde(default)]
+    lock: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CloseThreadToolOutput {
+    confirmation: MessageId,
+    status: String,
+}
+
+impl CloseThreadTool {
+    pub fn new(closer: Arc<ThreadCloser>, filter: Arc<OutboundFilter>) -> Self {
+        Self {
+            closer,
+            filter
+        }
+    }
+}
+
+impl Tool for CloseThreadTool {
+    const NAME: &'static str = CLOSE_THREAD_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = Args;
+    type Output = ToolResponse<CloseThreadToolOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(Args)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Asks the thread owner to confirm that their question is resolved.  If \
+                they confirm, the summary is posted and the thread is closed.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.summary.trim().is_empty() {
+            return Ok(ToolResponse::rejected("summary must not be empty"));
+        }
+
+        let length = args.summary.chars().count();
+        if length > SUMMARY_LIMIT {
+            return Ok(ToolResponse::rejected(format!("summary is {length} characters long, the limit is {SUMMARY_LIMIT}")));
+        }
+
+        let warning = match self.filter.check(Self::NAME, [&mut args.summary]) {
+            Ok(warning) => warning,
+            Err(e) => return Ok(ToolResponse::rejected(e)),
+        };
+
+        let msg = self.closer
+            .request(CloseRequest {
+                summary: args.summary,
+                lock: args.lock,
+            }).await
+            .map_err(ResponseError::SerenityError)?;
+
+        Ok(ToolResponse::with_warning(CloseThreadToolOutput {
+            confirmation: msg.id,
+            status: "The owner has been asked to confirm, the thread will be closed if they do".to_string(),
+        }, warning))
+    }
+}
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp::schemars::schema_for;
+use coral_rs::rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use serenity::all::{EditMessage, GuildChannel, Http, MessageId};
+use crate::discord::outbound_filter::OutboundFilter;
+use crate::discord::sent_messages::SentMessages;
+use crate::discord::tools::{ResponseError, ThreadRespondToolOutput, ToolResponse};
+
+pub const THREAD_EDIT_TOOL_NAME: &str = "edit_discord_message";
+pub const THREAD_DELETE_TOOL_NAME: &str = "delete_discord_message";
+
+pub struct ThreadEditTool {
+    http: Arc<Http>,
+    channel: GuildChannel,
+    sent_messages: Arc<SentMessages>,
+    filter: Arc<OutboundFilter>
+}
+
+pub struct ThreadDeleteTool {
+    http: Arc<Http>,
+    channel: GuildChannel,
+    sent_messages: Arc<SentMessages>
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct EditArgs {
+    #[schemars(description = "The id of the message to edit, as returned when it was sent")]
+    id: String,
+
+    #[schemars(description = "The new message content, replacing the old content entirely")]
+    content: String,
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct DeleteArgs {
+    #[schemars(description = "The id of the message to delete, as returned when it was sent")]
+    id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ThreadDeleteToolOutput {
+    deleted: MessageId,
+}
+
+impl ThreadEditTool {
+    pub fn new(
+        http: Arc<Http>,
+        channel: GuildChannel,
+        sent_messages: Arc<SentMessages>,
+        filter: Arc<OutboundFilter>,
+    ) -> Self {
+        Self {
+            http,
+            channel,
+            sent_messages,
+            filter
+        }
+    }
+}
+
+impl ThreadDeleteTool {
+    pub fn new(
+        http: Arc<Http>,
+        channel: GuildChannel,
+        sent_messages: Arc<SentMessages>,
+    ) -> Self {
+        Self {
+            http,
+            channel,
+            sent_messages
+        }
+    }
+}
+
+/// Parses a message id given by the model, making sure that it refers to a message that this agent
+/// sent
+async fn own_message_id(sent_messages: &SentMessages, id: &str) -> Result<MessageId, String> {
+    let id = id.trim().parse::<MessageId>()
+        .map_err(|_| format!("\"{id}\" is not a valid message id"))?;
+
+    if sent_messages.contains(id).await {
+        Ok(id)
+    }
+    else {
+        Err(format!("message {id} was not sent by you, only your own messages can be changed"))
+    }
+}
+
+impl Tool for ThreadEditTool {
+    const NAME: &'static str = THREAD_EDIT_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = EditArgs;
+    type Output = ToolResponse<ThreadRespondToolOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(EditArgs)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Replaces the content of a message you previously sent to the Discord \
+                thread.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let id = match own_message_id(&self.sent_messages, &args.id).await {
+            Ok(id) => id,
+            Err(e) => return Ok(ToolResponse::rejected(e)),
+        };
+
+        let warning = match self.filter.check(Self::NAME, [&mut args.content]) {
+            Ok(warning) => warning,
+            Err(e) => return Ok(ToolResponse::rejected(e)),
+        };
+
+        let msg = self.channel
+            .edit_message(&self.http, id, EditMessage::new().content(args.content)).await
+            .map_err(ResponseError::SerenityError)?;
+
+        Ok(ToolResponse::with_warning(ThreadRespondToolOutput {
+            id: msg.id,
+            timestamp: msg.edited_timestamp.unwrap_or(msg.timestamp)
+        }, warning))
+    }
+}
+
+impl Tool for ThreadDeleteTool {
+    const NAME: &'static str = THREAD_DELETE_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = DeleteArgs;
+    type Output = ToolResponse<ThreadDeleteToolOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(DeleteArgs)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Deletes a message you previously sent to the Discord thread.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let id = match own_message_id(&self.sent_messages, &args.id).await {
+            Ok(id) => id,
+            Err(e) => return Ok(ToolResponse::rejected(e)),
+        };
+
+        self.channel.id
+            .delete_message(&self.http, id).await
+            .map_err(ResponseError::SerenityError)?;
+
+        self.sent_messages.remove(id).await;
+
+        Ok(ToolResponse::Ok(ThreadDeleteToolOutput {
+            deleted: id
+        }))
+    }
+}
@@ -0,0 +1,423 @@
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp::schemars::schema_for;
+use coral_rs::rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use serenity::all::{Colour, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateMessage, GuildChannel, Http};
+use crate::discord::outbound_filter::OutboundFilter;
+use crate::discord::sent_messages::SentMessages;
+use crate::discord::tools::{ResponseError, ThreadRespondToolOutput, ToolResponse};
+
+pub const THREAD_EMBED_TOOL_NAME: &str = "send_discord_embed";
+
+// https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+const EMBED_TITLE_LIMIT: usize = 256;
+const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+const EMBED_FIELD_COUNT_LIMIT: usize = 25;
+const EMBED_FIELD_NAME_LIMIT: usize = 256;
+const EMBED_FIELD_VALUE_LIMIT: usize = 1024;
+const EMBED_FOOTER_LIMIT: usize = 2048;
+const EMBED_TOTAL_LIMIT: usize = 6000;
+
+// https://discord.com/developers/docs/interactions/message-components#button-object
+const LINK_BUTTON_COUNT_LIMIT: usize = 5;
+const LINK_BUTTON_LABEL_LIMIT: usize = 80;
+
+pub struct ThreadEmbedTool {
+    http: Arc<Http>,
+    channel: GuildChannel,
+    sent_messages: Arc<SentMessages>,
+    filter: Arc<OutboundFilter>
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct EmbedField {
+    #[schemars(description = "The field name, shown in bold above the value")]
+    name: String,
+
+    #[schemars(description = "The field value, markdown is supported")]
+    value: String,
+
+    #[schemars(description = "Whether this field can be displayed on the same line as other inline fields")]
+    #[serde(default)]
+    inline: bool,
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct EmbedLink {
+    #[schemars(description = "The text shown on the link button")]
+    label: String,
+
+    #[schemars(description = "The http or https URL the button opens")]
+    url: String,
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct Args {
+    #[schemars(description = "The embed title")]
+    title: String,
+
+    #[schemars(description = "The embed body, markdown is supported")]
+    description: Option<String>,
+
+    #[schemars(description = "An http or https URL that the title links to")]
+    url: Option<String>,
+
+    #[schemars(description = "Fields shown below the description, useful for steps or version tables")]
+    #[serde(default)]
+    fields: Vec<EmbedField>,
+
+    #[schemars(description = "The embed color as a hex string, e.g. #5865F2")]
+    color: Option<String>,
+
+    #[schemars(description = "Small text shown at the bottom of the embed")]
+    footer: Option<String>,
+
+    #[schemars(description = "Links shown as buttons below the embed, e.g. documentation pages")]
+    #[serde(default)]
+    links: Vec<EmbedLink>,
+}
+
+impl ThreadEmbedTool {
+    pub fn new(
+        http: Arc<Http>,
+        channel: GuildChannel,
+        sent_messages: Arc<SentMessages>,
+        filter: Arc<OutboundFilter>,
+    ) -> Self {
+        Self {
+            http,
+            channel,
+            sent_messages,
+            filter
+        }
+    }
+}
+
+impl Args {
+    /// All text shown to users in the embed
+    fn texts(&mut self) -> impl Iterator<Item = &mut String> {
+        std::iter::once(&mut self.title)
+            .chain(self.description.as_mut())
+            .chain(self.footer.as_mut())
+            .chain(self.fields.iter_mut().flat_map(|field| [&mut field.name, &mut field.value]))
+            .chain(self.links.iter_mut().map(|link| &mut link.label))
+    }
+
+    /// URLs in the embed, these are filtered without being redacted
+    fn urls(&self) -> impl Iterator<Item = &str> {
+        self.url
+            .as_deref()
+            .into_iter()
+            .chain(self.links.iter().map(|link| link.url.as_str()))
+    }
+}
+
+fn check_length(name: &str, value: &str, limit: usize) -> Result<usize, String> {
+    let length = value.chars().count();
+    if length > limit {
+        Err(format!("{name} is {length} characters long, the limit is {limit}"))
+    }
+    else {
+        Ok(length)
+    }
+}
+
+fn check_url(name: &str, url: &str) -> Result<(), String> {
+    if url.starts_with("https://") || url.starts_with("http://") {
+        Ok(())
+    }
+    else {
+        Err(format!("{name} \"{url}\" must start with http:// or https://"))
+    }
+}
+
+pub fn parse_color(color: &str) -> Result<Colour, String> {
+    let hex = color.trim_start_matches('#');
+    // from_str_radix accepts a leading sign
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("color \"{color}\" is not a hex color like #5865F2"));
+    }
+
+    Ok(Colour::new(u32::from_str_radix(hex, 16).unwrap()))
+}
+
+impl Args {
+    /// Validates these arguments against Discord's embed limits, returning a message that the
+    /// model can act on if they are exceeded
+    fn validate(&self) -> Result<(), String> {
+        let mut total = check_length("title", &self.title, EMBED_TITLE_LIMIT)?;
+        if self.title.trim().is_empty() {
+            return Err("title must not be empty".to_string());
+        }
+
+        if let Some(description) = &self.description {
+            total += check_length("description", description, EMBED_DESCRIPTION_LIMIT)?;
+        }
+
+        if let Some(url) = &self.url {
+            check_url("url", url)?;
+        }
+
+        if self.fields.len() > EMBED_FIELD_COUNT_LIMIT {
+            return Err(format!("{} fields were given, the limit is {EMBED_FIELD_COUNT_LIMIT}",
+                self.fields.len()));
+        }
+
+        for (i, field) in self.fields.iter().enumerate() {
+            if field.name.trim().is_empty() || field.value.trim().is_empty() {
+                return Err(format!("field {i} must have a non-empty name and value"));
+            }
+
+            total += check_length(&format!("field {i} name"), &field.name, EMBED_FIELD_NAME_LIMIT)?;
+            total += check_length(&format!("field {i} value"), &field.value, EMBED_FIELD_VALUE_LIMIT)?;
+        }
+
+        if let Some(color) = &self.color {
+            parse_color(color)?;
+        }
+
+        if let Some(footer) = &self.footer {
+            total += check_length("footer", footer, EMBED_FOOTER_LIMIT)?;
+        }
+
+        if total > EMBED_TOTAL_LIMIT {
+            return Err(format!("the embed is {total} characters long in total, the limit is {EMBED_TOTAL_LIMIT}"));
+        }
+
+        if self.links.len() > LINK_BUTTON_COUNT_LIMIT {
+            return Err(format!("{} links were given, the limit is {LINK_BUTTON_COUNT_LIMIT}",
+                self.links.len()));
+        }
+
+        for (i, link) in self.links.iter().enumerate() {
+            if link.label.trim().is_empty() {
+                return Err(format!("link {i} must have a non-empty label"));
+            }
+
+            check_length(&format!("link {i} label"), &link.label, LINK_BUTTON_LABEL_LIMIT)?;
+            check_url(&format!("link {i} url"), &link.url)?;
+        }
+
+        Ok(())
+    }
+
+    fn into_message(self) -> CreateMessage {
+        let mut embed = CreateEmbed::new()
+            .title(self.title)
+            .fields(self.fields
+                .into_iter()
+                .map(|field| (field.name, field.value, field.inline)));
+
+        if let Some(description) = self.description {
+            embed = embed.description(description);
+        }
+
+        if let Some(url) = self.url {
+            embed = embed.url(url);
+        }
+
+        if let Some(color) = self.color.as_deref().and_then(|x| parse_color(x).ok()) {
+            embed = embed.color(color);
+        }
+
+        if let Some(footer) = self.footer {
+            embed = embed.footer(CreateEmbedFooter::new(footer));
+        }
+
+        let mut message = CreateMessage::new()
+            .embed(embed);
+
+        if !self.links.is_empty() {
+            message = message.components(vec![CreateActionRow::Buttons(self.links
+                .into_iter()
+                .map(|link| CreateButton::new_link(link.url).label(link.label))
+                .collect())]);
+        }
+
+        message
+    }
+}
+
+impl Tool for ThreadEmbedTool {
+    const NAME: &'static str = THREAD_EMBED_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = Args;
+    type Output = ToolResponse<ThreadRespondToolOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(Args)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Sends a rich embed to the Discord thread.  Use this for answers that \
+                include documentation links, step lists or version tables.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if let Err(e) = args.validate() {
+            return Ok(ToolResponse::rejected(e));
+        }
+
+        if let Err(e) = self.filter.check_verbatim(Self::NAME, args.urls()) {
+            return Ok(ToolResponse::rejected(e));
+        }
+
+        let warning = match self.filter.check(Self::NAME, args.texts()) {
+            Ok(warning) => warning,
+            Err(e) => return Ok(ToolResponse::rejected(e)),
+        };
+
+        let msg = self.channel
+            .send_message(&self.http, args.into_message()).await
+            .map_err(ResponseError::SerenityError)?;
+
+        self.sent_messages.insert(msg.id).await;
+
+        Ok(ToolResponse::with_warning(ThreadRespondToolOutput {
+            id: msg.id,
+            timestamp: msg.timestamp
+        }, warning))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> Args {
+        Args {
+            title: "Installing Coral".to_string(),
+            description: Some("Follow the steps below".to_string()),
+            url: Some("https://docs.coralprotocol.org".to_string()),
+            fields: Vec::new(),
+            color: Some("#5865F2".to_string()),
+            footer: None,
+            links: Vec::new(),
+        }
+    }
+
+    fn field(value: &str) -> EmbedField {
+        EmbedField {
+            name: "Step".to_string(),
+            value: value.to_string(),
+            inline: false,
+        }
+    }
+
+    fn link(url: &str) -> EmbedLink {
+        EmbedLink {
+            label: "Docs".to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn valid_embed() {
+        let mut args = args();
+        args.fields = vec![field("Install the server")];
+        args.links = vec![link("https://docs.coralprotocol.org")];
+        assert_eq!(args.validate(), Ok(()));
+    }
+
+    #[test]
+    fn empty_title() {
+        let mut args = args();
+        args.title = "  ".to_string();
+        assert_eq!(args.validate(), Err("title must not be empty".to_string()));
+    }
+
+    #[test]
+    fn field_count_limit() {
+        let mut args = args();
+        args.fields = (0..EMBED_FIELD_COUNT_LIMIT).map(|_| field("x")).collect();
+        assert_eq!(args.validate(), Ok(()));
+
+        args.fields.push(field("x"));
+        assert_eq!(args.validate(), Err("26 fields were given, the limit is 25".to_string()));
+    }
+
+    #[test]
+    fn empty_field_value() {
+        let mut args = args();
+        args.fields = vec![field("x"), field("")];
+        assert_eq!(args.validate(), Err("field 1 must have a non-empty name and value".to_string()));
+    }
+
+    #[test]
+    fn total_length_limit() {
+        let mut args = args();
+        args.title = "t".repeat(EMBED_TITLE_LIMIT);
+        args.description = Some("d".repeat(EMBED_DESCRIPTION_LIMIT));
+        args.footer = Some("f".repeat(EMBED_TOTAL_LIMIT - EMBED_TITLE_LIMIT - EMBED_DESCRIPTION_LIMIT));
+        assert_eq!(args.validate(), Ok(()));
+
+        args.fields = vec![field("x")];
+        assert_eq!(args.validate(), Err("the embed is 6005 characters long in total, the limit is 6000".to_string()));
+    }
+
+    #[test]
+    fn length_is_counted_in_characters() {
+        let mut args = args();
+        args.title = "é".repeat(EMBED_TITLE_LIMIT);
+        assert_eq!(args.validate(), Ok(()));
+    }
+
+    #[test]
+    fn link_limits() {
+        let mut args = args();
+        args.links = (0..LINK_BUTTON_COUNT_LIMIT).map(|_| link("https://coralprotocol.org")).collect();
+        assert_eq!(args.validate(), Ok(()));
+
+        args.links.push(link("https://coralprotocol.org"));
+        assert_eq!(args.validate(), Err("6 links were given, the limit is 5".to_string()));
+
+        args.links = vec![EmbedLink {
+            label: "l".repeat(LINK_BUTTON_LABEL_LIMIT + 1),
+            url: "https://coralprotocol.org".to_string(),
+        }];
+        assert_eq!(args.validate(), Err("link 0 label is 81 characters long, the limit is 80".to_string()));
+    }
+
+    #[test]
+    fn urls_must_be_http() {
+        let mut args = args();
+        args.links = vec![link("ftp://coralprotocol.org")];
+        assert_eq!(args.validate(), Err("link 0 url \"ftp://coralprotocol.org\" must start with http:// or https://".to_string()));
+
+        args.links.clear();
+        args.url = Some("javascript:alert(1)".to_string());
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#5865F2"), Ok(Colour::new(0x5865F2)));
+        assert_eq!(parse_color("5865f2"), Ok(Colour::new(0x5865F2)));
+        assert!(parse_color("#FFF").is_err());
+        assert!(parse_color("#GGGGGG").is_err());
+        assert!(parse_color("blue").is_err());
+        assert!(parse_color("+5865F").is_err());
+        assert!(parse_color("#-5865F").is_err());
+    }
+
+    #[tokio::test]
+    async fn secrets_in_urls_are_rejected() {
+        let tool = ThreadEmbedTool::new(
+            Arc::new(Http::new("")),
+            GuildChannel::default(),
+            Arc::new(SentMessages::new()),
+            Arc::new(OutboundFilter::new(Vec::new())),
+        );
+
+        let mut args = args();
+        args.links = vec![link(&format!("https://coralprotocol.org/?token=ghp_{}", "A".repeat(36)))];
+        match tool.call(args).await {
+            Ok(ToolResponse::Rejected { error }) => assert!(error.contains("a GitHub token"), "{error}"),
+            _ => panic!("the embed was not rejected"),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp::schemars::schema_for;
+use coral_rs::rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use crate::discord::escalation::Escalation;
+use crate::discord::outbound_filter::OutboundFilter;
+use crate::discord::tools::{ResponseError, ThreadRespondToolOutput, ToolResponse};
+
+pub const ESCALATE_TO_STAFF_TOOL_NAME: &str = "escalate_to_staff";
+
+// https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+const SUMMARY_LIMIT: usize = 3000;
+
+pub struct EscalateToStaffTool {
+    escalation: Arc<Escalation>,
+    filter: Arc<OutboundFilter>
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct Args {
+    #[schemars(description = "A summary for staff of the user's problem and what has been tried so far")]
+    summary: String,
+}
+
+impl EscalateToStaffTool {
+    pub fn new(escalation: Arc<Escalation>, filter: Arc<OutboundFilter>) -> Self {
+        Self {
+            escalation,
+            filter
+        }
+    }
+}
+
+impl Tool for EscalateToStaffTool {
+    const NAME: &'static str = ESCALATE_TO_STAFF_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = Args;
+    type Output = ToolResponse<ThreadRespondToolOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(Args)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Asks human staff to take over this support thread and tells the user \
+                that a human is on the way.  You will not receive new messages until staff respond \
+                or hand the thread back.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if !self.escalation.is_configured() {
+            return Ok(ToolResponse::rejected("escalating to staff is not configured, continue helping the user"));
+        }
+
+        if self.escalation.is_paused() {
+            return Ok(ToolResponse::rejected("this thread has already been escalated to staff"));
+        }
+
+        if args.summary.trim().is_empty() {
+            return Ok(ToolResponse::rejected("summary must not be empty"));
+        }
+
+        let length = args.summary.chars().count();
+        if length > SUMMARY_LIMIT {
+            return Ok(ToolResponse::rejected(format!("summary is {length} characters long, the limit is {SUMMARY_LIMIT}")));
+        }
+
+        let warning = match self.filter.check(Self::NAME, [&mut args.summary]) {
+            Ok(warning) => warning,
+            Err(e) => return Ok(ToolResponse::rejected(e)),
+        };
+
+        let msg = self.escalation
+            .escalate(args.summary).await
+            .map_err(ResponseError::SerenityError)?;
+
+        Ok(ToolResponse::with_warning(ThreadRespondToolOutput {
+            id: msg.id,
+            timestamp: msg.timestamp
+        }, warning))
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp::schemars::schema_for;
+use coral_rs::rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use serenity::all::{GetMessages, GuildChannel, Http, MessageId};
+use crate::discord::thread_message::ThreadMessage;
+use crate::discord::tools::{ResponseError, ToolResponse};
+
+pub const READ_THREAD_HISTORY_TOOL_NAME: &str = "read_thread_history";
+
+// https://discord.com/developers/docs/resources/message#get-channel-messages
+const HISTORY_LIMIT: i64 = 100;
+const DEFAULT_HISTORY_LIMIT: i64 = 25;
+
+pub struct ReadThreadHistoryTool {
+    http: Arc<Http>,
+    channel: GuildChannel
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct Args {
+    #[schemars(description = "Only return messages sent before the message with this id.  Leave empty to start from the newest message")]
+    before: Option<String>,
+
+    #[schemars(description = "The maximum number of messages to return, between 1 and 100.  Defaults to 25")]
+    // Signed and wider than the limit allows so that an out of range value, negative or not, is
+    // rejected rather than failing to deserialize, which would end the agent loop
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ReadThreadHistoryToolOutput {
+    /// Oldest message first
+    messages: Vec<ThreadMessage>,
+
+    /// Pass this as before to read the page of messages before this one
+    next_before: Option<MessageId>,
+}
+
+impl ReadThreadHistoryTool {
+    pub fn new(
+        http: Arc<Http>,
+        channel: GuildChannel,
+    ) -> Self {
+        Self {
+            http,
+            channel
+        }
+    }
+}
+
+impl Tool for ReadThreadHistoryTool {
+    const NAME: &'static str = READ_THREAD_HISTORY_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = Args;
+    type Output = ToolResponse<ReadThreadHistoryToolOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(Args)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Reads earlier messages from the Discord thread, newest page first.  Use \
+                this to re-read details that are no longer in your context.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let limit = args.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+        if !(1..=HISTORY_LIMIT).contains(&limit) {
+            return Ok(ToolResponse::rejected(format!("limit must be between 1 and {HISTORY_LIMIT}")));
+        }
+
+        let mut request = GetMessages::new().limit(limit as u8);
+        if let Some(before) = args.before.as_deref().filter(|x| !x.trim().is_empty()) {
+            match before.trim().parse::<MessageId>() {
+                Ok(before) => request = request.before(before),
+                Err(_) => return Ok(ToolResponse::rejected(format!("\"{before}\" is not a valid message id"))),
+            }
+        }
+
+        // Messages are returned newest first
+        let messages = self.channel
+            .messages(&self.http, request).await
+            .map_err(ResponseError::SerenityError)?;
+
+        let next_before = if messages.len() == limit as usize {
+            messages.last().map(|x| x.id)
+        }
+        else {
+            None
+        };
+
+        Ok(ToolResponse::Ok(ReadThreadHistoryToolOutput {
+            messages: messages
+                .into_iter()
+                .rev()
+                .map(ThreadMessage::from)
+                .collect(),
+            next_before,
+        }))
+    }
+}
@@ -0,0 +1,138 @@
+pub mod embed;
+pub mod edit;
+pub mod attachment;
+pub mod close;
+pub mod tags;
+pub mod escalate;
+pub mod clarify;
+pub mod history;
+pub mod search;
+
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp as rmcp;
+use coral_rs::rmcp::schemars::schema_for;
+use rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use serenity::all::{CreateMessage, GuildChannel, Http, MessageId, Timestamp};
+use crate::discord::outbound_filter::OutboundFilter;
+use crate::discord::sent_messages::SentMessages;
+
+pub const THREAD_RESPOND_TOOL_NAME: &str = "send_discord_message";
+
+pub struct ThreadRespondTool {
+    http: Arc<Http>,
+    channel: GuildChannel,
+    sent_messages: Arc<SentMessages>,
+    filter: Arc<OutboundFilter>
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Response error")]
+pub enum ResponseError {
+    #[error("Discord error: {0}")]
+    SerenityError(serenity::Error)
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct Args {
+    #[schemars(description = "The message content")]
+    content: String,
+}
+
+impl ThreadRespondTool {
+    pub fn new(
+        http: Arc<Http>,
+        channel: GuildChannel,
+        sent_messages: Arc<SentMessages>,
+        filter: Arc<OutboundFilter>,
+    ) -> Self {
+        Self {
+            http,
+            channel,
+            sent_messages,
+            filter
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ThreadRespondToolOutput {
+    id: MessageId,
+    timestamp: Timestamp,
+}
+
+/// Output for tools that validate their arguments before sending anything to Discord.
+///
+/// A tool returning an error ends the agent loop, so problems the model can fix itself (too long
+/// descriptions, invalid URLs, etc.) are returned as a successful output containing the reason
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ToolResponse<T> {
+    Ok(T),
+    Rejected {
+        error: String
+    },
+    Warning {
+        #[serde(flatten)]
+        output: T,
+        warning: String
+    }
+}
+
+impl<T> ToolResponse<T> {
+    pub fn rejected(error: impl Into<String>) -> Self {
+        Self::Rejected {
+            error: error.into()
+        }
+    }
+
+    /// A successful output, with a warning for the model if the tool changed what it was asked to
+    /// send
+    pub fn with_warning(output: T, warning: Option<String>) -> Self {
+        match warning {
+            Some(warning) => Self::Warning {
+                output,
+                warning
+            },
+            None => Self::Ok(output)
+        }
+    }
+}
+
+impl Tool for ThreadRespondTool {
+    const NAME: &'static str = THREAD_RESPOND_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = Args;
+    type Output = ToolResponse<ThreadRespondToolOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(Args)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Sends a message to the Discord thread.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let warning = match self.filter.check(Self::NAME, [&mut args.content]) {
+            Ok(warning) => warning,
+            Err(e) => return Ok(ToolResponse::rejected(e)),
+        };
+
+        let message = CreateMessage::new().content(args.content);
+        let msg = self.channel
+            .send_message(&self.http, message).await
+            .map_err(ResponseError::SerenityError)?;
+
+        self.sent_messages.insert(msg.id).await;
+
+        Ok(ToolResponse::with_warning(ThreadRespondToolOutput {
+            id: msg.id,
+            timestamp: msg.timestamp
+        }, warning))
+    }
+}
\ No newline at end of file
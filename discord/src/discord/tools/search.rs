@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp::schemars::schema_for;
+use coral_rs::rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use crate::discord::thread_search::ThreadSearch;
+use crate::discord::tools::{ResponseError, ToolResponse};
+
+pub const SEARCH_PAST_THREADS_TOOL_NAME: &str = "search_past_threads";
+
+const SEARCH_LIMIT: usize = 10;
+const DEFAULT_SEARCH_LIMIT: usize = 5;
+
+pub struct SearchPastThreadsTool {
+    search: Arc<ThreadSearch>
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct Args {
+    #[schemars(description = "Keywords describing the problem, e.g. \"docker compose port conflict\"")]
+    query: String,
+
+    #[schemars(description = "The maximum number of threads to return, between 1 and 10.  Defaults to 5")]
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct PastThreadOutput {
+    title: String,
+    link: String,
+    resolution: Option<String>,
+}
+
+impl SearchPastThreadsTool {
+    pub fn new(search: Arc<ThreadSearch>) -> Self {
+        Self {
+            search
+        }
+    }
+}
+
+impl Tool for SearchPastThreadsTool {
+    const NAME: &'static str = SEARCH_PAST_THREADS_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = Args;
+    type Output = ToolResponse<Vec<PastThreadOutput>>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(Args)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Searches previously resolved support threads in this forum by keyword, \
+                returning their titles, links and how they were resolved.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let limit = args.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        if limit == 0 || limit > SEARCH_LIMIT {
+            return Ok(ToolResponse::rejected(format!("limit must be between 1 and {SEARCH_LIMIT}")));
+        }
+
+        if args.query.trim().is_empty() {
+            return Ok(ToolResponse::rejected("query must not be empty"));
+        }
+
+        let results = self.search
+            .search(&args.query, limit).await
+            .map_err(ResponseError::SerenityError)?;
+
+        Ok(ToolResponse::Ok(results
+            .into_iter()
+            .map(|result| PastThreadOutput {
+                title: result.title,
+                link: result.link,
+                resolution: result.snippet,
+            })
+            .collect()))
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+use coral_rs::rig::completion::ToolDefinition;
+use coral_rs::rig::tool::Tool;
+use coral_rs::rmcp::schemars::schema_for;
+use coral_rs::rmcp::schemars as schemars;
+use serde::{Deserialize, Serialize};
+use crate::discord::forum_tags::{ForumTags, APPLIED_TAG_LIMIT};
+use crate::discord::tools::{ResponseError, ToolResponse};
+
+pub const LIST_FORUM_TAGS_TOOL_NAME: &str = "list_forum_tags";
+pub const APPLY_FORUM_TAGS_TOOL_NAME: &str = "apply_forum_tags";
+
+pub struct ListForumTagsTool {
+    forum_tags: Arc<ForumTags>
+}
+
+pub struct ApplyForumTagsTool {
+    forum_tags: Arc<ForumTags>
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct ListArgs {}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+pub struct ApplyArgs {
+    #[schemars(description = "The names of the tags to apply")]
+    tags: Vec<String>,
+
+    #[schemars(description = "If true, every tag you are allowed to change that is not in tags is removed.  If false, tags are added to the existing tags")]
+    #[serde(default)]
+    replace: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ForumTagOutput {
+    name: String,
+    applied: bool,
+    allowed: bool,
+}
+
+impl ListForumTagsTool {
+    pub fn new(forum_tags: Arc<ForumTags>) -> Self {
+        Self {
+            forum_tags
+        }
+    }
+}
+
+impl ApplyForumTagsTool {
+    pub fn new(forum_tags: Arc<ForumTags>) -> Self {
+        Self {
+            forum_tags
+        }
+    }
+}
+
+async fn list_tags(forum_tags: &ForumTags) -> serenity::Result<Vec<ForumTagOutput>> {
+    let applied = forum_tags.applied().await?;
+    Ok(forum_tags.available().await?
+        .into_iter()
+        .map(|tag| ForumTagOutput {
+            applied: applied.contains(&tag.id),
+            allowed: forum_tags.is_allowed(&tag.name),
+            name: tag.name,
+        })
+        .collect())
+}
+
+impl Tool for ListForumTagsTool {
+    const NAME: &'static str = LIST_FORUM_TAGS_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = ListArgs;
+    type Output = Vec<ForumTagOutput>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(ListArgs)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Lists the tags available in the Discord forum, whether they are applied \
+                to this thread and whether you are allowed to change them.".to_string(),
+            parameters,
+        }
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        list_tags(&self.forum_tags).await
+            .map_err(ResponseError::SerenityError)
+    }
+}
+
+impl Tool for ApplyForumTagsTool {
+    const NAME: &'static str = APPLY_FORUM_TAGS_TOOL_NAME;
+    type Error = ResponseError;
+    type Args = ApplyArgs;
+    type Output = ToolResponse<Vec<ForumTagOutput>>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let parameters = serde_json::to_value(schema_for!(ApplyArgs)).unwrap();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!("Adds or replaces tags on this Discord thread.  Only tags listed \
+                as allowed by {LIST_FORUM_TAGS_TOOL_NAME} can be changed."),
+            parameters,
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let available = self.forum_tags.available().await
+            .map_err(ResponseError::SerenityError)?;
+        let applied = self.forum_tags.applied().await
+            .map_err(ResponseError::SerenityError)?;
+
+        let mut requested = Vec::new();
+        for name in &args.tags {
+            let Some(tag) = available.iter().find(|tag| tag.name.eq_ignore_ascii_case(name)) else {
+                return Ok(ToolResponse::rejected(format!("the tag \"{name}\" does not exist in this forum")));
+            };
+
+            if !self.forum_tags.is_allowed(&tag.name) {
+                return Ok(ToolResponse::rejected(format!("you are not allowed to change the tag \"{}\"", tag.name)));
+            }
+
+            requested.push(tag.id);
+        }
+
+        // Tags outside the allowlist are never removed, even when replacing
+        let mut tags = applied
+            .into_iter()
+            .filter(|id| !args.replace || available
+                .iter()
+                .find(|tag| tag.id == *id)
+                .is_none_or(|tag| !self.forum_tags.is_allowed(&tag.name)))
+            .collect::<Vec<_>>();
+
+        for id in requested {
+            if !tags.contains(&id) {
+                tags.push(id);
+            }
+        }
+
+        if tags.len() > APPLIED_TAG_LIMIT {
+            return Ok(ToolResponse::rejected(format!("a thread can have at most {APPLIED_TAG_LIMIT} tags, this change would apply {}",
+                tags.len())));
+        }
+
+        self.forum_tags.set_applied(tags).await
+            .map_err(ResponseError::SerenityError)?;
+
+        list_tags(&self.forum_tags).await
+            .map(ToolResponse::Ok)
+            .map_err(ResponseError::SerenityError)
+    }
+}
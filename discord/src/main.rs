@@ -1,28 +1,70 @@
 mod discord;
 mod timeout;
-
+mod template;
+mod provider;
+mod prompt;
+mod context;
+mod usage;
+mod transcript;
+mod startup;
+
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Arc;
-use crate::discord::thread_watcher::{ShardManagerContainer, ThreadEventHandler, ThreadWatcher};
-use clap::{arg, Parser};
+use crate::discord::thread_watcher::{ThreadEventHandler, ThreadWatcher};
+use clap::Parser;
 use coral_rs::agent::Agent;
-use coral_rs::agent_loop::AgentLoop;
 use coral_rs::completion_evaluated_prompt::CompletionEvaluatedPrompt;
 use coral_rs::init_tracing;
 use coral_rs::mcp_server::McpConnectionBuilder;
-use coral_rs::rig::client::{CompletionClient, ProviderClient};
-use coral_rs::rig::providers::openrouter;
-use coral_rs::rig::providers::openai::GPT_4_1_MINI;
-use coral_rs::telemetry::TelemetryMode;
-use futures::stream;
-use serenity::all::{ChannelId, GatewayIntents, GetMessages};
+use coral_rs::error::Error;
+use coral_rs::mcp_server::McpServerConnection;
+use coral_rs::rig::agent::AgentBuilder;
+use coral_rs::rig::client::CompletionClient;
+use coral_rs::rig::completion::CompletionModel;
+use coral_rs::rig::providers::{anthropic, openai, openrouter};
+use futures::future::LocalBoxFuture;
+use futures::{stream, FutureExt, Stream};
+use serde_json::json;
+use serenity::all::{ChannelId, GatewayIntents, GetMessages, RoleId};
 use serenity::Client;
 use tokio::select;
-use tracing::log::info;
+use tracing::log::{error, info, warn};
 use crate::discord::thread_message::ThreadMessage;
 use crate::discord::tools::THREAD_RESPOND_TOOL_NAME;
+use crate::discord::tools::embed::ThreadEmbedTool;
+use crate::discord::tools::edit::{ThreadDeleteTool, ThreadEditTool};
+use crate::discord::tools::attachment::ThreadAttachmentTool;
+use crate::discord::sent_messages::SentMessages;
+use crate::discord::progress::Progress;
+use crate::discord::forum_tags::ForumTags;
+use crate::discord::close::{CloseOptions, ThreadCloser};
+use crate::discord::shutdown::{Shutdown, ShutdownReason};
+use crate::discord::escalation::Escalation;
+use crate::discord::clarification::Clarifications;
+use crate::discord::survey::Survey;
+use crate::discord::tools::clarify::AskChoiceTool;
+use crate::discord::tools::history::ReadThreadHistoryTool;
+use crate::discord::thread_search::ThreadSearch;
+use crate::discord::outbound_filter::OutboundFilter;
+use crate::discord::tools::search::SearchPastThreadsTool;
+use crate::discord::tools::escalate::EscalateToStaffTool;
+use crate::discord::tools::close::CloseThreadTool;
+use crate::discord::tools::tags::{ApplyForumTagsTool, ListForumTagsTool};
 
 use crate::discord::tools::ThreadRespondTool;
 use crate::timeout::Timeout;
+use crate::timeout::stages::Stages;
+use crate::provider::Provider;
+use crate::prompt::{SystemPrompt, ThreadContext};
+use crate::context::{ContextManager, ContextOptions};
+use crate::context::agent_loop::ContextAgentLoop;
+use crate::context::summarizer::ModelSummarizer;
+use crate::usage::{MeteredModel, Prices, UsageTracker};
+use crate::discord::budget::{Budget, BudgetAction};
+use crate::transcript::Transcript;
+use crate::startup::StartupError;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -43,14 +85,249 @@ struct Arguments {
     /// the warning time
     #[arg(long, env = "DISCORD_TIMEOUT")]
     timeout_duration: humantime::Duration,
+
+    /// A TOML file with the notifications posted while the thread is inactive, one [[stage]] per
+    /// notification.  The warning and timeout durations are ignored if this is set
+    #[arg(long, env = "DISCORD_TIMEOUT_STAGES")]
+    timeout_stages: Option<PathBuf>,
+
+    /// Whether threads that time out are locked as well as archived
+    #[arg(long, env = "DISCORD_TIMEOUT_LOCK")]
+    timeout_lock: bool,
+
+    /// The name of the forum tag applied to threads that time out
+    #[arg(long, env = "DISCORD_TIMEOUT_TAG")]
+    timeout_tag: Option<String>,
+
+    /// The name of the forum tag applied to threads closed with the close thread tool
+    #[arg(long, env = "DISCORD_RESOLVED_TAG")]
+    resolved_tag: Option<String>,
+
+    /// A comma separated list of forum tag names that the agent is allowed to apply or remove
+    #[arg(long, env = "DISCORD_TAG_ALLOWLIST", value_delimiter = ',')]
+    tag_allowlist: Vec<String>,
+
+    /// The role pinged when the agent escalates a thread to staff.  A reply from a member with this
+    /// role resumes automatic replies in an escalated thread
+    #[arg(long, env = "DISCORD_STAFF_ROLE_ID")]
+    staff_role_id: Option<RoleId>,
+
+    /// The channel that escalations are posted to.  If this is not set, escalations are posted in
+    /// the support thread
+    #[arg(long, env = "DISCORD_STAFF_CHANNEL_ID")]
+    staff_channel_id: Option<ChannelId>,
+
+    /// The name of the forum tag applied to threads escalated to staff
+    #[arg(long, env = "DISCORD_STAFF_TAG")]
+    staff_tag: Option<String>,
+
+    /// How long the satisfaction survey posted when the thread closes stays open.  Set to 0s to
+    /// disable the survey
+    #[arg(long, env = "DISCORD_SURVEY_DURATION")]
+    survey_duration: humantime::Duration,
+
+    /// The file that survey responses are appended to, one JSON object per line
+    #[arg(long, env = "DISCORD_SURVEY_STORE")]
+    survey_store: PathBuf,
+
+    /// A comma separated list of phrases that the agent is never allowed to send.  Messages
+    /// containing any of these are blocked
+    #[arg(long, env = "DISCORD_BANNED_PHRASES", value_delimiter = ',')]
+    banned_phrases: Vec<String>,
+
+    /// A file with the agent's system prompt, using {name} variables for the thread and tool names.
+    /// The built-in prompt in system-prompt.md is used if this is not set
+    #[arg(long, env = "DISCORD_SYSTEM_PROMPT")]
+    system_prompt: Option<PathBuf>,
+
+    /// The provider the agent's model is run on
+    #[arg(long, env = "DISCORD_PROVIDER", value_enum)]
+    provider: Provider,
+
+    /// The base URL of the provider's API, used to point the openai provider at an OpenAI
+    /// compatible server.  The provider's own API is used if this is not set
+    #[arg(long, env = "DISCORD_BASE_URL")]
+    base_url: Option<String>,
+
+    /// The API key used with the openrouter provider
+    #[arg(long, env = "OPENROUTER_API_KEY", hide_env_values = true)]
+    openrouter_api_key: Option<String>,
+
+    /// The API key used with the openai provider.  Not required if a base URL is set
+    #[arg(long, env = "OPENAI_API_KEY", hide_env_values = true)]
+    openai_api_key: Option<String>,
+
+    /// The API key used with the anthropic provider
+    #[arg(long, env = "ANTHROPIC_API_KEY", hide_env_values = true)]
+    anthropic_api_key: Option<String>,
+
+    /// The id of the model the agent runs on, as the provider names it
+    #[arg(long, env = "DISCORD_MODEL")]
+    model: String,
+
+    /// The id of a model to use if the main model is unavailable, only supported by OpenRouter
+    #[arg(long, env = "DISCORD_FALLBACK_MODEL")]
+    fallback_model: Option<String>,
+
+    /// The sampling temperature of the model
+    #[arg(long, env = "DISCORD_TEMPERATURE")]
+    temperature: f64,
+
+    /// The maximum number of tokens the model can generate in one completion
+    #[arg(long, env = "DISCORD_MAX_TOKENS")]
+    max_tokens: u64,
+
+    /// The maximum number of queued thread messages given to the agent in one turn
+    #[arg(long, env = "DISCORD_BATCH_SIZE")]
+    batch_size: NonZeroUsize,
+
+    /// How many tokens the agent's conversation can grow to before older turns are summarized.  This
+    /// does not include the system prompt, previous messages or Coral resources in the preamble
+    #[arg(long, env = "DISCORD_CONTEXT_BUDGET")]
+    context_budget: usize,
+
+    /// How many of the most recent turns are kept verbatim when the conversation is summarized
+    #[arg(long, env = "DISCORD_CONTEXT_RECENT_TURNS")]
+    context_recent_turns: NonZeroUsize,
+
+    /// The maximum number of tokens in the summary of older turns.  The summary keeps growing over
+    /// a long thread, so this should be well above the agent's own max tokens
+    #[arg(long, env = "DISCORD_SUMMARY_MAX_TOKENS")]
+    summary_max_tokens: u64,
+
+    /// A TOML file with the price of each model in USD per million tokens, used to log what a
+    /// thread cost
+    #[arg(long, env = "DISCORD_PRICE_TABLE")]
+    price_table: Option<PathBuf>,
+
+    /// The most a thread can cost in USD before the budget action is taken.  Requires a price for
+    /// the model in the price table
+    #[arg(long, env = "DISCORD_BUDGET")]
+    budget: Option<f64>,
+
+    /// What happens when a thread goes over its budget
+    #[arg(long, env = "DISCORD_BUDGET_ACTION", value_enum)]
+    budget_action: BudgetAction,
+
+    /// The directory a transcript of the thread is written to when the agent shuts down, as JSON
+    /// and Markdown.  No transcript is written if this is not set
+    #[arg(long, env = "DISCORD_TRANSCRIPT_DIR")]
+    transcript_dir: Option<PathBuf>,
+
+    /// The Coral session this agent is running in
+    #[arg(long, env = "CORAL_SESSION_ID")]
+    session_id: Option<String>,
+}
+
+impl Arguments {
+    /// The API key for the selected provider
+    fn api_key(&self) -> Option<&str> {
+        match self.provider {
+            Provider::OpenRouter => self.openrouter_api_key.as_deref(),
+            Provider::OpenAi => self.openai_api_key.as_deref(),
+            Provider::Anthropic => self.anthropic_api_key.as_deref(),
+        }
+    }
+}
+
+/// The tools given to the agent.  The agent's type depends on the provider, so the tools are
+/// gathered here before it is known which builder they are added to
+struct AgentTools {
+    respond: ThreadRespondTool,
+    embed: ThreadEmbedTool,
+    edit: ThreadEditTool,
+    delete: ThreadDeleteTool,
+    attachment: ThreadAttachmentTool,
+    close: CloseThreadTool,
+    list_tags: ListForumTagsTool,
+    apply_tags: ApplyForumTagsTool,
+    escalate: EscalateToStaffTool,
+    ask_choice: AskChoiceTool,
+    history: ReadThreadHistoryTool,
+    search: SearchPastThreadsTool,
+}
+
+/// Everything the agent is built with apart from the model
+struct AgentParts {
+    tools: AgentTools,
+    preamble: CompletionEvaluatedPrompt,
+    coral: McpServerConnection,
+    transcript: Arc<Transcript>,
+}
+
+/// Finishes building the agent on the provider's model and returns its loop over `prompt_stream`
+fn agent_loop<M: CompletionModel + 'static>(
+    model: M,
+    additional_params: Option<serde_json::Value>,
+    parts: AgentParts,
+    args: &Arguments,
+    prompt_stream: impl Stream<Item = CompletionEvaluatedPrompt> + 'static,
+) -> LocalBoxFuture<'static, Result<(), Error>> {
+    let AgentParts { tools, preamble, coral, transcript } = parts;
+    let summarizer = ModelSummarizer::new(model.clone(), args.summary_max_tokens, additional_params.clone());
+    let context = ContextManager::new(summarizer, ContextOptions {
+        budget: args.context_budget,
+        recent_turns: args.context_recent_turns.get(),
+    });
+
+    let mut builder = AgentBuilder::new(model);
+    if let Some(additional_params) = additional_params {
+        builder = builder.additional_params(additional_params);
+    }
+
+    let completion_agent = builder
+        .tool(tools.respond)
+        .tool(tools.embed)
+        .tool(tools.edit)
+        .tool(tools.delete)
+        .tool(tools.attachment)
+        .tool(tools.close)
+        .tool(tools.list_tags)
+        .tool(tools.apply_tags)
+        .tool(tools.escalate)
+        .tool(tools.ask_choice)
+        .tool(tools.history)
+        .tool(tools.search)
+        .temperature(args.temperature)
+        .max_tokens(args.max_tokens)
+        .build();
+
+    let agent = Agent::new(completion_agent)
+        .preamble(preamble)
+        .telemetry(args.provider.telemetry_mode(), &args.model)
+        .mcp_server(coral);
+
+    ContextAgentLoop::new(agent, context, transcript, prompt_stream)
+        .execute()
+        .boxed_local()
 }
 
 #[tokio::main]
-async fn main() {
-    init_tracing().expect("Failed to set up tracing");
+async fn main() -> ExitCode {
+    if let Err(e) = init_tracing() {
+        eprintln!("Failed to set up tracing: {e}");
+        return ExitCode::FAILURE;
+    }
 
     let args = Arguments::parse();
 
+    match run(&args).await {
+        Ok(reason) => {
+            info!("Agent shut down: {reason:?}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            let exit_code = e.exit_code();
+            tracing::error!(exit_code, thread_id = %args.thread_id, error = %e, "Agent failed to start");
+            e.notify_thread(&args.api_token, args.thread_id).await;
+            ExitCode::from(exit_code)
+        }
+    }
+}
+
+/// Sets up the agent and runs it until the thread is finished with.  Anything that stops the agent
+/// from starting is returned as a [`StartupError`] rather than a panic
+async fn run(args: &Arguments) -> Result<ShutdownReason, StartupError> {
     /*
         Discord
      */
@@ -60,40 +337,135 @@ async fn main() {
     let mut client = Client::builder(&args.api_token, intents)
         .event_handler(ThreadEventHandler)
         .await
-        .expect("Error creating discord client");
+        .map_err(StartupError::DiscordClient)?;
 
     let channel = client.http.get_channel(args.thread_id)
-        .await.expect("The specified thread does not exist")
-        .guild().expect("The specified thread is not in a guild");
+        .await.map_err(|error| StartupError::thread(args.thread_id, error))?
+        .guild().ok_or(StartupError::NotAGuildThread(args.thread_id))?;
 
     let metadata = channel.thread_metadata
-        .expect("The thread is missing thread metadata");
+        .ok_or(StartupError::MissingThreadMetadata(channel.id))?;
 
-    let owner_id = channel.owner_id.expect("The thread is missing an owner");
-    let mut existing_messages = channel.messages(&client.http, GetMessages::new())
-        .await.expect("Failed to get existing thread messages")
+    if metadata.archived || metadata.locked {
+        return Err(StartupError::ThreadClosed(channel.id));
+    }
+
+    let owner_id = channel.owner_id.ok_or(StartupError::MissingOwner(channel.id))?;
+    let bot_id = client.http.get_current_user()
+        .await.map_err(|error| StartupError::Discord { what: "bot user", error })?
+        .id;
+
+    // Newest message first
+    let messages = channel.messages(&client.http, GetMessages::new())
+        .await.map_err(|error| StartupError::Discord { what: "existing thread messages", error })?;
+    let mut existing_messages = messages
         .iter()
         .map(ThreadMessage::from)
         .collect::<Vec<_>>();
 
-    if metadata.archived || metadata.locked {
-        panic!("The specified thread is archived or locked");
+    let system_prompt = SystemPrompt::load(args.system_prompt.as_deref())?;
+
+    let price = args.price_table
+        .as_deref()
+        .map(Prices::load)
+        .transpose()?
+        .and_then(|prices| prices.get(&args.model));
+    if price.is_none() && args.budget.is_some() {
+        warn!("There is no price for {} in the price table, the budget cannot be enforced", args.model);
     }
 
+    let usage = Arc::new(UsageTracker::new(price));
+
+    let guild = client.http.get_guild(channel.guild_id)
+        .await.map_err(|error| StartupError::Discord { what: "thread's guild", error })?;
+
+    // Forum post guidelines are the forum channel's topic
+    let guidelines = match channel.parent_id {
+        Some(parent_id) => client.http.get_channel(parent_id)
+            .await.map_err(|error| StartupError::Discord { what: "thread's parent channel", error })?
+            .guild()
+            .and_then(|parent| parent.topic)
+            .unwrap_or_default(),
+        None => String::new(),
+    };
+
+    let stages = match &args.timeout_stages {
+        Some(path) => Stages::load(path)?,
+        None => Stages::single_warning(args.timeout_duration_warning.into(), args.timeout_duration.into()),
+    };
+
     let timeout = Arc::new(Timeout::new(
-        args.timeout_duration_warning.into(),
-        args.timeout_duration.into(),
+        stages,
+        owner_id,
+        client.http.clone(),
+        channel.clone(),
+    ));
+    timeout.restore(&messages, bot_id);
+
+    let progress = Arc::new(Progress::new(client.http.clone(), channel.id));
+    let transcript = Arc::new(Transcript::new(channel.id, channel.name.clone(), owner_id, args.session_id.clone()));
+    let watcher = Arc::new(ThreadWatcher::new(channel.clone(), timeout.clone(), progress.clone(), transcript.clone()));
+    let forum_tags = Arc::new(ForumTags::new(
+        client.http.clone(),
+        channel.id,
+        channel.parent_id,
+        args.tag_allowlist.clone(),
+    ));
+    let survey = Arc::new(Survey::new(
+        client.http.clone(),
+        channel.clone(),
+        owner_id,
+        args.session_id.clone(),
+        args.survey_duration.into(),
+        args.survey_store.clone(),
+    ));
+
+    let shutdown = Arc::new(Shutdown::new(client.shard_manager.clone()));
+    let closer = Arc::new(ThreadCloser::new(
         client.http.clone(),
         channel.clone(),
+        owner_id,
+        shutdown.clone(),
+        forum_tags.clone(),
+        survey.clone(),
+        CloseOptions {
+            resolved_tag: args.resolved_tag.clone(),
+            timeout_lock: args.timeout_lock,
+            timeout_tag: args.timeout_tag.clone(),
+        },
     ));
 
-    let watcher = Arc::new(ThreadWatcher::new(channel.clone(), timeout.clone()));
+    let escalation = Arc::new(Escalation::new(
+        client.http.clone(),
+        channel.clone(),
+        owner_id,
+        args.staff_role_id,
+        args.staff_channel_id,
+        args.staff_tag.clone(),
+        forum_tags.clone(),
+    ));
+
+    let clarifications = Arc::new(Clarifications::new(client.http.clone(), channel.clone(), owner_id));
+
     {
         let mut data = client.data.write().await;
         data.insert::<ThreadWatcher>(watcher.clone());
-        data.insert::<ShardManagerContainer>(client.shard_manager.clone());
+        data.insert::<Shutdown>(shutdown.clone());
+        data.insert::<ThreadCloser>(closer.clone());
+        data.insert::<Escalation>(escalation.clone());
+        data.insert::<Clarifications>(clarifications.clone());
+        data.insert::<Survey>(survey.clone());
+        data.insert::<Timeout>(timeout.clone());
     }
 
+    /*
+        Coral
+     */
+    // Connected to before the gateway is started, so that a failed connection leaves nothing running
+    let coral = McpConnectionBuilder::from_coral_env()
+        .connect()
+        .await?;
+
     let http = client.http.clone();
     let discord_handle = tokio::spawn(async move {
         client
@@ -101,47 +473,29 @@ async fn main() {
             .await
     });
 
-    /*
-        Coral
-     */
-    let coral = McpConnectionBuilder::from_coral_env()
-        .connect()
-        .await.expect("Failed to connect to the Coral server");
-
     let mut preamble = CompletionEvaluatedPrompt::new()
-        .string(format!(r#"
-You a Coral agent tasked with assisting {owner_id} in a support thread.
-
-# Workflow for every new message:
-1. Create a Coral thread for this support thread if one doesn't already exist
-2. Determine which agents can help with the {owner_id}'s request
-3. Alert {owner_id} using {THREAD_RESPOND_TOOL_NAME} that you are going ask other agents and that it make take some time
-4. Communicate with the agents determined in step 2.
-5. Summarise information from other agents and provide it to {owner_id} using {THREAD_RESPOND_TOOL_NAME}
-
-# Support tips
-1. If a message looks incomplete, wait for the user to follow-up
-2. Prioritise responding to {owner_id}, other users may send messages in the same support thread
-
-# Discord tips
-1. Some or all of the the user's query may exist as the title of the thread
-2. Markdown and emojis are supported, notifying users can be done with the <@userid> syntax, e.g <@{owner_id}>
-3. The platform and communication on it is generally informal
-
-# Discord thread information
-Title: {}
-Owner: {owner_id}
-"#, channel.name));
+        .string(system_prompt.render(&ThreadContext {
+            owner_id,
+            title: &channel.name,
+            guild: &guild.name,
+            guidelines: &guidelines,
+        }));
 
     // A discord thread is spawned with one message, so take the last message and send it to the
-    // message queue so that it is processed as a loop prompt
-    let last_message = existing_messages.pop()
-        .expect("No existing messages found on the created thread");
+    // message queue so that it is processed as a loop prompt.  If the agent has already replied in
+    // this thread it has been restarted, and only the messages sent since its last reply still need
+    // a response
+    let pending_messages = match messages.iter().position(|x| x.author.id == bot_id) {
+        Some(last_reply) => existing_messages.drain(..last_reply).rev().collect(),
+        None => vec![existing_messages.pop()
+            .ok_or(StartupError::NoMessages(channel.id))?],
+    };
 
     info!("Responding to thread: {}", channel.name);
-    info!("With message body: {}", last_message.content);
-
-    let _ = watcher.sender.lock().await.send(last_message);
+    for message in pending_messages {
+        info!("With message body: {}", message.content);
+        let _ = watcher.queue(message).await;
+    }
 
     // If there are more messages (happens if the support agent joins late or if they are re-added
     // to the thread), attach the messages to the additional_prompting string
@@ -150,7 +504,7 @@ Owner: {owner_id}
         preamble = preamble.string(existing_messages
             .iter()
             .rev()
-            .flat_map(|x| serde_json::to_string(x))
+            .flat_map(serde_json::to_string)
             .collect::<Vec<_>>()
             .join("\n")
             .as_str());
@@ -159,26 +513,68 @@ Owner: {owner_id}
     // Add coral resources
     preamble = preamble.all_resources(coral.clone());
 
-    let model = GPT_4_1_MINI;
-    let completion_agent = openrouter::Client::from_env()
-        .agent(model)
-        .tool(ThreadRespondTool::new(http.clone(), channel))
-        .temperature(0.30)
-        .max_tokens(512)
-        .build();
+    let thread_search = Arc::new(ThreadSearch::new(
+        http.clone(),
+        &channel,
+        forum_tags.clone(),
+        args.resolved_tag.clone(),
+    ));
 
-    let agent = Agent::new(completion_agent)
-        .preamble(preamble)
-        .telemetry(TelemetryMode::OpenAI, model)
-        .mcp_server(coral);
+    let sent_messages = Arc::new(SentMessages::new());
+    let filter = Arc::new(OutboundFilter::new(args.banned_phrases.clone()));
+    let budget = Arc::new(Budget::new(
+        usage.clone(),
+        args.budget,
+        args.budget_action,
+        escalation.clone(),
+        shutdown.clone(),
+        http.clone(),
+        channel.clone(),
+    ));
+    let tools = AgentTools {
+        respond: ThreadRespondTool::new(http.clone(), channel.clone(), sent_messages.clone(), filter.clone()),
+        embed: ThreadEmbedTool::new(http.clone(), channel.clone(), sent_messages.clone(), filter.clone()),
+        edit: ThreadEditTool::new(http.clone(), channel.clone(), sent_messages.clone(), filter.clone()),
+        delete: ThreadDeleteTool::new(http.clone(), channel.clone(), sent_messages.clone()),
+        attachment: ThreadAttachmentTool::new(http.clone(), channel.clone(), sent_messages.clone(), filter.clone()),
+        close: CloseThreadTool::new(closer.clone(), filter.clone()),
+        list_tags: ListForumTagsTool::new(forum_tags.clone()),
+        apply_tags: ApplyForumTagsTool::new(forum_tags),
+        escalate: EscalateToStaffTool::new(escalation.clone(), filter.clone()),
+        ask_choice: AskChoiceTool::new(clarifications, filter),
+        history: ReadThreadHistoryTool::new(http.clone(), channel),
+        search: SearchPastThreadsTool::new(thread_search),
+    };
+
+    // The prompt stream is polled again once the agent loop has finished a turn, so this is where
+    // the progress of the previous turn is completed and the progress of the next turn is started.
+    // The inactivity timeout is paused for the duration of a turn and restarted from the last
+    // message the agent sent.  Messages are left in the queue and the timeout stays paused while the
+    // thread is escalated to staff.
+    // The budget is checked between turns, the stream ends if the agent has to stop
+    let batch_size = args.batch_size.get();
+    let turn_state = (watcher.receiver.clone(), progress, escalation, timeout.clone(), sent_messages, budget);
+    let prompt_stream = stream::unfold(turn_state, move |(receiver, progress, escalation, timeout, sent_messages, budget)| async move {
+        progress.finish_turn().await;
+
+        if !budget.finish_turn().await {
+            return None;
+        }
+
+        escalation.wait_for_turn(&timeout, sent_messages.last_sent().await).await;
 
-    let prompt_stream = stream::unfold(watcher.receiver.clone(), |receiver| async move {
         let mut messages = Vec::new();
-        if receiver.lock().await.recv_many(&mut messages, 16).await == 0 {
+        if receiver.lock().await.recv_many(&mut messages, batch_size).await == 0 {
             None
         }
         else {
             info!("Received {} messages", messages.len());
+            progress.start_turn(messages
+                .iter()
+                .filter(|x| x.choice.is_none())
+                .map(|x| x.id)
+                .collect()).await;
+            timeout.pause();
 
             let prompt = CompletionEvaluatedPrompt::new()
                 .string("[START OF AUTOMATED MESSAGE]")
@@ -190,24 +586,120 @@ Owner: {owner_id}
                     .join("\n"))
                 .string("[END OF AUTOMATED MESSAGE]");
 
-            Some((prompt, receiver))
+            Some((prompt, (receiver, progress, escalation, timeout, sent_messages, budget)))
         }
     });
 
-    let agent_handle = AgentLoop::new(agent, prompt_stream)
-        .execute();
+    // Local OpenAI compatible servers usually do not check the key
+    let api_key = match args.api_key() {
+        Some(api_key) => api_key,
+        None if args.provider == Provider::OpenAi && args.base_url.is_some() => "",
+        None => return Err(StartupError::MissingApiKey {
+            variable: args.provider.api_key_variable(),
+            provider: args.provider,
+        }),
+    };
+
+    if args.fallback_model.is_some() && !args.provider.supports_fallback() {
+        warn!("A fallback model is only supported by OpenRouter, {:?} will not fall back", args.provider);
+    }
+
+    let parts = AgentParts {
+        tools,
+        preamble,
+        coral,
+        transcript: transcript.clone(),
+    };
+
+    let agent_handle = match args.provider {
+        Provider::OpenRouter => {
+            let mut client = openrouter::Client::builder(api_key);
+            if let Some(base_url) = &args.base_url {
+                client = client.base_url(base_url);
+            }
+
+            let model = client
+                .build().map_err(|error| StartupError::ProviderClient { provider: Provider::OpenRouter, error })?
+                .completion_model(&args.model);
+
+            // OpenRouter moves on to the next model in the list if a model is down or rejects the
+            // request
+            let additional_params = args.fallback_model
+                .as_ref()
+                .map(|fallback_model| json!({
+                    "models": [&args.model, fallback_model]
+                }));
+
+            agent_loop(MeteredModel::new(model, usage.clone()), additional_params, parts, args, prompt_stream)
+        },
+        Provider::OpenAi => {
+            let mut client = openai::Client::builder(api_key);
+            if let Some(base_url) = &args.base_url {
+                client = client.base_url(base_url);
+            }
+
+            // Servers that stand in for OpenAI generally only implement the chat completions API
+            let model = client
+                .build().map_err(|error| StartupError::ProviderClient { provider: Provider::OpenAi, error })?
+                .completion_model(&args.model)
+                .completions_api();
+
+            agent_loop(MeteredModel::new(model, usage.clone()), None, parts, args, prompt_stream)
+        },
+        Provider::Anthropic => {
+            let mut client = anthropic::Client::builder(api_key);
+            if let Some(base_url) = &args.base_url {
+                client = client.base_url(base_url);
+            }
+
+            let model = client
+                .build().map_err(|error| StartupError::ProviderClient { provider: Provider::Anthropic, error })?
+                .completion_model(&args.model);
 
-    let timeout_handle = timeout.run();
+            agent_loop(MeteredModel::new(model, usage.clone()), None, parts, args, prompt_stream)
+        },
+    };
+
+    // The timeout closes the thread in its own task, the agent loop has to stay alive until the
+    // survey is over and the thread is archived
+    let timeout_handle = tokio::spawn({
+        let closer = closer.clone();
+        async move {
+            timeout.run().await;
+            info!("Timeout reached");
+            closer.time_out().await;
+        }
+    });
 
-    select! {
-        _ = agent_handle => {
-            info!("Agent thread exited")
+    // select drops the other branches before running the branch that finished.  Closing the thread
+    // happens outside of this select, so the agent loop is only dropped once shutdown has stopped
+    // the Discord gateway.  If the agent loop or the gateway stops on its own, the other one is
+    // stopped here
+    let reason = select! {
+        result = agent_handle => {
+            match result {
+                Ok(()) => info!("Agent thread exited"),
+                Err(e) => error!("Agent thread exited with an error: {e}"),
+            }
+            shutdown.shutdown(ShutdownReason::AgentExited).await;
+            shutdown.wait().await
         },
         _ = discord_handle => {
-            info!("Discord thread exited")
+            info!("Discord thread exited");
+            shutdown.shutdown(ShutdownReason::GatewayClosed).await;
+            shutdown.wait().await
         },
-        _ = timeout_handle => {
-            info!("Timeout reached")
+        reason = shutdown.wait() => reason,
+    };
+    timeout_handle.abort();
+
+    usage.log_totals();
+    if let Some(dir) = &args.transcript_dir {
+        match transcript.export(dir, reason) {
+            Ok(path) => info!("Transcript written to {}", path.display()),
+            Err(e) => error!("Failed to write transcript: {e}"),
         }
     }
+
+    Ok(reason)
 }
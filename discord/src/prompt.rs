@@ -0,0 +1,136 @@
+use std::path::Path;
+use serenity::all::UserId;
+use crate::discord::tools::THREAD_RESPOND_TOOL_NAME;
+use crate::discord::tools::attachment::THREAD_ATTACHMENT_TOOL_NAME;
+use crate::discord::tools::clarify::ASK_CHOICE_TOOL_NAME;
+use crate::discord::tools::close::CLOSE_THREAD_TOOL_NAME;
+use crate::discord::tools::edit::{THREAD_DELETE_TOOL_NAME, THREAD_EDIT_TOOL_NAME};
+use crate::discord::tools::embed::THREAD_EMBED_TOOL_NAME;
+use crate::discord::tools::escalate::ESCALATE_TO_STAFF_TOOL_NAME;
+use crate::discord::tools::history::READ_THREAD_HISTORY_TOOL_NAME;
+use crate::discord::tools::search::SEARCH_PAST_THREADS_TOOL_NAME;
+use crate::discord::tools::tags::{APPLY_FORUM_TAGS_TOOL_NAME, LIST_FORUM_TAGS_TOOL_NAME};
+use crate::template::{Template, TemplateError};
+
+/// The system prompt used when no template is configured
+pub const DEFAULT_SYSTEM_PROMPT: &str = include_str!("../system-prompt.md");
+
+/// Variables describing the thread, their values are only known once the agent has started
+const OWNER_VARIABLE: &str = "owner";
+const TITLE_VARIABLE: &str = "title";
+const GUILD_VARIABLE: &str = "guild";
+const GUIDELINES_VARIABLE: &str = "guidelines";
+
+/// Variables for the name of each tool, so that a template does not need to know the names
+const TOOL_VARIABLES: &[(&str, &str)] = &[
+    ("respond_tool", THREAD_RESPOND_TOOL_NAME),
+    ("embed_tool", THREAD_EMBED_TOOL_NAME),
+    ("edit_tool", THREAD_EDIT_TOOL_NAME),
+    ("delete_tool", THREAD_DELETE_TOOL_NAME),
+    ("attachment_tool", THREAD_ATTACHMENT_TOOL_NAME),
+    ("close_tool", CLOSE_THREAD_TOOL_NAME),
+    ("list_tags_tool", LIST_FORUM_TAGS_TOOL_NAME),
+    ("apply_tags_tool", APPLY_FORUM_TAGS_TOOL_NAME),
+    ("escalate_tool", ESCALATE_TO_STAFF_TOOL_NAME),
+    ("ask_choice_tool", ASK_CHOICE_TOOL_NAME),
+    ("history_tool", READ_THREAD_HISTORY_TOOL_NAME),
+    ("search_tool", SEARCH_PAST_THREADS_TOOL_NAME),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SystemPromptError {
+    #[error("could not read system prompt: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid system prompt: {0}")]
+    Template(#[from] TemplateError),
+}
+
+/// Where the thread the agent is assisting in is
+pub struct ThreadContext<'a> {
+    pub owner_id: UserId,
+    pub title: &'a str,
+    pub guild: &'a str,
+
+    /// The post guidelines of the parent forum, empty if there are none
+    pub guidelines: &'a str,
+}
+
+/// The agent's persona, workflow and tips, rendered into the preamble once the thread is known
+pub struct SystemPrompt {
+    template: Template,
+}
+
+impl SystemPrompt {
+    /// Loads the template at `path`, or the built-in default if there is no path
+    pub fn load(path: Option<&Path>) -> Result<Self, SystemPromptError> {
+        match path {
+            Some(path) => Self::parse(&std::fs::read_to_string(path)?),
+            None => Self::parse(DEFAULT_SYSTEM_PROMPT),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, SystemPromptError> {
+        let variables = [OWNER_VARIABLE, TITLE_VARIABLE, GUILD_VARIABLE, GUIDELINES_VARIABLE]
+            .into_iter()
+            .chain(TOOL_VARIABLES.iter().map(|(variable, _)| *variable))
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            template: Template::parse(source, &variables)?
+        })
+    }
+
+    pub fn render(&self, thread: &ThreadContext) -> String {
+        let owner = thread.owner_id.to_string();
+        let values = [
+            (OWNER_VARIABLE, owner.as_str()),
+            (TITLE_VARIABLE, thread.title),
+            (GUILD_VARIABLE, thread.guild),
+            (GUIDELINES_VARIABLE, thread.guidelines),
+        ];
+
+        self.template.render(&values
+            .into_iter()
+            .chain(TOOL_VARIABLES.iter().copied())
+            .collect::<Vec<_>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thread() -> ThreadContext<'static> {
+        ThreadContext {
+            owner_id: UserId::new(42),
+            title: "Agent does not start",
+            guild: "Coral",
+            guidelines: "Include your version",
+        }
+    }
+
+    #[test]
+    fn default_prompt_is_valid() {
+        let prompt = SystemPrompt::load(None).unwrap().render(&thread());
+        assert!(prompt.contains("assisting 42 in a support thread"));
+        assert!(prompt.contains(&format!("using {THREAD_RESPOND_TOOL_NAME}")));
+        assert!(prompt.contains("Title: Agent does not start"));
+        assert!(!prompt.contains('{'));
+    }
+
+    #[test]
+    fn every_variable_is_rendered() {
+        let prompt = SystemPrompt::parse("{owner} {title} {guild} {guidelines} {search_tool}").unwrap();
+        assert_eq!(
+            prompt.render(&thread()),
+            format!("42 Agent does not start Coral Include your version {SEARCH_PAST_THREADS_TOOL_NAME}")
+        );
+    }
+
+    #[test]
+    fn unknown_variable_is_an_error() {
+        let error = SystemPrompt::parse("Hello {ownr}").err().unwrap();
+        assert!(matches!(error, SystemPromptError::Template(TemplateError::UnknownVariable { ref name, .. }) if name == "ownr"));
+    }
+}
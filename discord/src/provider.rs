@@ -0,0 +1,44 @@
+use clap::ValueEnum;
+use coral_rs::telemetry::TelemetryMode;
+
+/// The LLM provider the agent's model is run on
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Provider {
+    /// OpenRouter, models are ids such as openai/gpt-4.1-mini
+    #[value(name = "openrouter")]
+    OpenRouter,
+
+    /// OpenAI or any endpoint with an OpenAI compatible chat completions API, such as a local vLLM
+    /// or Ollama server
+    #[value(name = "openai")]
+    OpenAi,
+
+    /// Anthropic's messages API
+    #[value(name = "anthropic")]
+    Anthropic,
+}
+
+impl Provider {
+    /// The environment variable the provider's API key is read from
+    pub fn api_key_variable(self) -> &'static str {
+        match self {
+            Provider::OpenRouter => "OPENROUTER_API_KEY",
+            Provider::OpenAi => "OPENAI_API_KEY",
+            Provider::Anthropic => "ANTHROPIC_API_KEY",
+        }
+    }
+
+    /// The format completions are reported to Coral in.  Anthropic's messages do not map cleanly
+    /// on to the OpenAI format, so they are sent as rig's own messages
+    pub fn telemetry_mode(self) -> TelemetryMode {
+        match self {
+            Provider::OpenRouter | Provider::OpenAi => TelemetryMode::OpenAI,
+            Provider::Anthropic => TelemetryMode::Generic,
+        }
+    }
+
+    /// Whether the provider can move on to a fallback model by itself
+    pub fn supports_fallback(self) -> bool {
+        self == Provider::OpenRouter
+    }
+}
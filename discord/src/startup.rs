@@ -0,0 +1,168 @@
+use coral_rs::rig::client::ClientBuilderError;
+use serenity::all::{ChannelId, CreateEmbed, CreateMessage, Http};
+use tracing::error;
+use crate::prompt::SystemPromptError;
+use crate::provider::Provider;
+use crate::timeout::stages::StagesError;
+use crate::usage::PricesError;
+
+/// Why the agent could not start.  Each reason exits with its own code so that whatever spawned the
+/// agent can tell them apart without reading the logs
+#[derive(Debug, thiserror::Error)]
+pub enum StartupError {
+    #[error("could not create the Discord client: {0}")]
+    DiscordClient(serenity::Error),
+
+    #[error("thread {id} does not exist or cannot be seen by the bot: {error}")]
+    ThreadNotFound {
+        id: ChannelId,
+        error: serenity::Error,
+    },
+
+    #[error("channel {0} is not a thread in a guild")]
+    NotAGuildThread(ChannelId),
+
+    #[error("thread {0} is missing thread metadata")]
+    MissingThreadMetadata(ChannelId),
+
+    #[error("thread {0} is missing an owner")]
+    MissingOwner(ChannelId),
+
+    #[error("thread {0} is archived or locked")]
+    ThreadClosed(ChannelId),
+
+    #[error("thread {0} has no messages")]
+    NoMessages(ChannelId),
+
+    #[error("could not get the {what}: {error}")]
+    Discord {
+        what: &'static str,
+        error: serenity::Error,
+    },
+
+    #[error(transparent)]
+    SystemPrompt(#[from] SystemPromptError),
+
+    #[error(transparent)]
+    TimeoutStages(#[from] StagesError),
+
+    #[error(transparent)]
+    PriceTable(#[from] PricesError),
+
+    #[error("{variable} must be set to use {provider:?}")]
+    MissingApiKey {
+        variable: &'static str,
+        provider: Provider,
+    },
+
+    #[error("could not create the {provider:?} client: {error}")]
+    ProviderClient {
+        provider: Provider,
+        error: ClientBuilderError,
+    },
+
+    #[error("could not connect to the Coral server: {0}")]
+    Coral(#[from] coral_rs::error::Error),
+}
+
+impl StartupError {
+    /// An error getting the thread, which only means the thread is missing if Discord said so
+    pub fn thread(id: ChannelId, error: serenity::Error) -> Self {
+        let status = match &error {
+            serenity::Error::Http(e) => e.status_code().map(|status| status.as_u16()),
+            _ => None,
+        };
+
+        match status {
+            Some(403 | 404) => StartupError::ThreadNotFound { id, error },
+            _ => StartupError::Discord { what: "thread", error },
+        }
+    }
+
+    /// The process exit code for this error.  1 and 101 are left to clap and panics
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            StartupError::DiscordClient(_) => 10,
+            StartupError::ThreadNotFound { .. } => 11,
+            StartupError::NotAGuildThread(_) => 12,
+            StartupError::MissingThreadMetadata(_) => 13,
+            StartupError::MissingOwner(_) => 14,
+            StartupError::ThreadClosed(_) => 15,
+            StartupError::NoMessages(_) => 16,
+            StartupError::Discord { .. } => 17,
+            StartupError::SystemPrompt(_) => 20,
+            StartupError::TimeoutStages(_) => 21,
+            StartupError::PriceTable(_) => 22,
+            StartupError::MissingApiKey { .. } => 30,
+            StartupError::ProviderClient { .. } => 31,
+            StartupError::Coral(_) => 40,
+        }
+    }
+
+    /// Whether the thread's owner should be told the agent could not start.  There is no point
+    /// posting in a thread that cannot be found or that has already been closed
+    fn should_notify_thread(&self) -> bool {
+        !matches!(self,
+            StartupError::DiscordClient(_)
+            | StartupError::ThreadNotFound { .. }
+            | StartupError::NotAGuildThread(_)
+            | StartupError::ThreadClosed(_)
+        )
+    }
+
+    /// Posts a short message in the thread saying the agent could not start, if it should
+    pub async fn notify_thread(&self, token: &str, thread_id: ChannelId) {
+        if !self.should_notify_thread() {
+            return;
+        }
+
+        let embed = CreateEmbed::new()
+            .title("⚠️ Support agent unavailable")
+            .description("The support agent could not start in this thread.  \
+                A member of staff can still help here.");
+        if let Err(e) = thread_id.send_message(Http::new(token), CreateMessage::new().embed(embed)).await {
+            error!("Error sending startup failure message: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn exit_codes_are_distinct() {
+        let thread = ChannelId::new(1);
+        let errors = [
+            StartupError::DiscordClient(serenity::Error::Other("client")),
+            StartupError::ThreadNotFound { id: thread, error: serenity::Error::Other("missing") },
+            StartupError::NotAGuildThread(thread),
+            StartupError::MissingThreadMetadata(thread),
+            StartupError::MissingOwner(thread),
+            StartupError::ThreadClosed(thread),
+            StartupError::NoMessages(thread),
+            StartupError::Discord { what: "bot user", error: serenity::Error::Other("user") },
+            StartupError::SystemPrompt(SystemPromptError::Io(std::io::ErrorKind::NotFound.into())),
+            StartupError::TimeoutStages(StagesError::Empty),
+            StartupError::PriceTable(PricesError::Io(std::io::ErrorKind::NotFound.into())),
+            StartupError::MissingApiKey { variable: "OPENROUTER_API_KEY", provider: Provider::OpenRouter },
+            StartupError::ProviderClient { provider: Provider::OpenAi, error: ClientBuilderError::InvalidProperty("base_url") },
+            StartupError::Coral(coral_rs::error::Error::BudgetExhausted),
+        ];
+
+        let codes = errors.iter().map(StartupError::exit_code).collect::<HashSet<_>>();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&0) && !codes.contains(&1) && !codes.contains(&101));
+    }
+
+    #[test]
+    fn only_open_threads_are_notified() {
+        let thread = ChannelId::new(1);
+        assert!(!StartupError::ThreadClosed(thread).should_notify_thread());
+        assert!(!StartupError::ThreadNotFound { id: thread, error: serenity::Error::Other("missing") }.should_notify_thread());
+        assert!(StartupError::NoMessages(thread).should_notify_thread());
+        assert!(StartupError::thread(thread, serenity::Error::Other("timed out")).should_notify_thread());
+        assert!(StartupError::Coral(coral_rs::error::Error::BudgetExhausted).should_notify_thread());
+    }
+}
@@ -0,0 +1,126 @@
+/// A piece of text from config with `{name}` placeholders.  Placeholders are checked against the
+/// variables that will be available when the template is parsed, so a typo in config is an error at
+/// startup rather than a literal `{ownr}` in the thread.  `{{` and `}}` are a literal brace.
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+enum Segment {
+    Text(String),
+    Variable(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("unknown variable \"{{{name}}}\", available variables are: {available}")]
+    UnknownVariable {
+        name: String,
+        available: String,
+    },
+
+    #[error("unclosed \"{{\" at byte {0}, use \"{{{{\" for a literal brace")]
+    Unclosed(usize),
+}
+
+impl Template {
+    pub fn parse(source: &str, variables: &[&str]) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|(_, c)| *c == '{').is_some() => text.push('{'),
+                '}' if chars.next_if(|(_, c)| *c == '}').is_some() => text.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) => name.push(c),
+                            None => return Err(TemplateError::Unclosed(i)),
+                        }
+                    }
+
+                    let name = name.trim().to_string();
+                    if !variables.contains(&name.as_str()) {
+                        return Err(TemplateError::UnknownVariable {
+                            name,
+                            available: variables.join(", "),
+                        });
+                    }
+
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Variable(name));
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self {
+            segments
+        })
+    }
+
+    /// Renders the template.  Variables without a value are left empty
+    pub fn render(&self, values: &[(&str, &str)]) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.as_str(),
+                Segment::Variable(name) => values
+                    .iter()
+                    .find(|(variable, _)| variable == name)
+                    .map(|(_, value)| *value)
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARIABLES: &[&str] = &["owner", "closes"];
+
+    #[test]
+    fn variables_are_rendered() {
+        let template = Template::parse("<@{owner}>, this closes { closes }.", VARIABLES).unwrap();
+        assert_eq!(template.render(&[("owner", "1"), ("closes", "soon")]), "<@1>, this closes soon.");
+    }
+
+    #[test]
+    fn missing_values_are_empty() {
+        let template = Template::parse("Closes {closes}", VARIABLES).unwrap();
+        assert_eq!(template.render(&[]), "Closes ");
+    }
+
+    #[test]
+    fn escaped_braces() {
+        let template = Template::parse("{{owner}} is {owner}, }} and {{", VARIABLES).unwrap();
+        assert_eq!(template.render(&[("owner", "1")]), "{owner} is 1, } and {");
+    }
+
+    #[test]
+    fn unclosed_brace() {
+        assert!(matches!(Template::parse("Hi {owner", VARIABLES), Err(TemplateError::Unclosed(3))));
+    }
+
+    #[test]
+    fn unknown_variable() {
+        match Template::parse("Hi {ownr}", VARIABLES) {
+            Err(TemplateError::UnknownVariable { name, available }) => {
+                assert_eq!(name, "ownr");
+                assert_eq!(available, "owner, closes");
+            }
+            _ => panic!("the template was parsed"),
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The source of time for [`TimeoutMachine`]
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn sleep_until(&self, deadline: Instant) -> impl Future<Output = ()> + Send;
+}
+
+/// Tokio's clock, which can be paused and advanced in tests with `tokio::time::pause`
+#[derive(Default, Clone, Copy)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep_until(deadline)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// No stage has been posted since the thread was last active
+    Idle,
+
+    /// `stage` was the last stage posted
+    Warned {
+        stage: usize
+    },
+
+    /// The last stage has been posted and the thread should be closed
+    Expired,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutEvent {
+    /// A stage before the last one is due.  The thread closes at `closes_at` if nothing happens
+    Warning {
+        stage: usize,
+        closes_at: Instant,
+    },
+
+    /// The last stage is due and the thread should be closed
+    Expired {
+        stage: usize,
+    },
+}
+
+/// The inactivity timeout without any Discord in it.  The machine only tracks which stage is next
+/// and when it is due, [`TimeoutMachine::poll`] must be called once the deadline has passed to
+/// advance it.
+///
+/// ```text
+/// Idle ──(first stage due)──▶ Warned ──(next stage due)──▶ Warned ──(last stage due)──▶ Expired
+///   ▲                           │
+///   └────(reset or resume)──────┘
+/// ```
+///
+/// While paused there is no deadline, resuming starts again from [`State::Idle`].  Warnings that are
+/// due after the thread should already have closed are skipped.
+pub struct TimeoutMachine<C: Clock> {
+    clock: C,
+
+    /// When each stage is due after the thread became inactive, the last entry expires the thread
+    schedule: Vec<Duration>,
+    state: State,
+
+    /// When the thread became inactive.  While paused, when the clock was paused
+    since: Instant,
+
+    /// How long the thread had already been inactive at `since`, when restored from an earlier run.
+    /// The monotonic clock cannot go back further than it has been running, which is not long after
+    /// a reboot, so the time is taken off the schedule instead of `since`
+    restored: Duration,
+    paused: bool,
+}
+
+impl<C: Clock> TimeoutMachine<C> {
+    /// `schedule` must not be empty and must be in ascending order
+    pub fn new(clock: C, schedule: Vec<Duration>) -> Self {
+        assert!(!schedule.is_empty(), "a timeout needs at least one stage");
+
+        Self {
+            since: clock.now(),
+            clock,
+            schedule,
+            state: State::Idle,
+            restored: Duration::ZERO,
+            paused: false,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    fn next_stage(&self) -> Option<usize> {
+        match self.state {
+            State::Idle => Some(0),
+            State::Warned { stage } => Some(stage + 1).filter(|stage| *stage < self.schedule.len()),
+            State::Expired => None,
+        }
+    }
+
+    /// When the next stage is due.  There is no deadline while paused or once expired
+    pub fn deadline(&self) -> Option<Instant> {
+        if self.paused {
+            return None;
+        }
+
+        self.next_stage().map(|stage| self.due(stage))
+    }
+
+    fn due(&self, stage: usize) -> Instant {
+        self.since + self.schedule[stage].saturating_sub(self.restored)
+    }
+
+    /// The thread was active, start again from now.  If the clock is paused, it stays paused
+    pub fn reset(&mut self) {
+        if self.state == State::Expired {
+            return;
+        }
+
+        self.since = self.clock.now();
+        self.restored = Duration::ZERO;
+        self.state = State::Idle;
+    }
+
+    /// Stops the clock, used while the agent is working on a turn
+    pub fn pause(&mut self) {
+        if self.state == State::Expired {
+            return;
+        }
+
+        self.since = self.clock.now();
+        self.paused = true;
+    }
+
+    /// Starts the clock again from the last message the agent sent while paused, or from now if it
+    /// sent nothing.  Does nothing if the clock is not paused.
+    pub fn resume(&mut self, last_sent: Option<Instant>) {
+        if !self.paused {
+            return;
+        }
+
+        let paused_at = self.since;
+        self.since = last_sent
+            .filter(|last_sent| *last_sent > paused_at)
+            .unwrap_or_else(|| self.clock.now());
+        self.restored = Duration::ZERO;
+        self.state = State::Idle;
+        self.paused = false;
+    }
+
+    /// Continues from an earlier run, where the thread has been inactive for `elapsed` and `posted`
+    /// is the last stage that was posted in that time
+    pub fn restore(&mut self, elapsed: Duration, posted: Option<usize>) {
+        self.since = self.clock.now();
+        self.restored = elapsed;
+        self.state = match posted {
+            None => State::Idle,
+            Some(stage) if stage + 1 >= self.schedule.len() => State::Expired,
+            Some(stage) => State::Warned { stage },
+        };
+    }
+
+    /// Advances to the next stage if it is due, returning the event for it
+    pub fn poll(&mut self) -> Option<TimeoutEvent> {
+        let deadline = self.deadline()?;
+        if self.clock.now() < deadline {
+            return None;
+        }
+
+        let stage = self.next_stage()?;
+        let last = self.schedule.len() - 1;
+        let closes_at = self.due(last);
+        if stage == last || self.clock.now() >= closes_at {
+            self.state = State::Expired;
+            Some(TimeoutEvent::Expired {
+                stage: last
+            })
+        }
+        else {
+            self.state = State::Warned { stage };
+            Some(TimeoutEvent::Warning {
+                stage,
+                closes_at,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::advance;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn machine() -> TimeoutMachine<TokioClock> {
+        TimeoutMachine::new(TokioClock, vec![minutes(30), minutes(50), minutes(60)])
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_until_first_stage_is_due() {
+        let mut machine = machine();
+        assert_eq!(machine.state(), State::Idle);
+
+        advance(minutes(29)).await;
+        assert_eq!(machine.poll(), None);
+        assert_eq!(machine.state(), State::Idle);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_to_warned() {
+        let start = Instant::now();
+        let mut machine = machine();
+
+        advance(minutes(30)).await;
+        assert_eq!(machine.poll(), Some(TimeoutEvent::Warning {
+            stage: 0,
+            closes_at: start + minutes(60),
+        }));
+        assert_eq!(machine.state(), State::Warned { stage: 0 });
+        assert_eq!(machine.poll(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn warned_to_warned() {
+        let start = Instant::now();
+        let mut machine = machine();
+
+        advance(minutes(30)).await;
+        machine.poll();
+        advance(minutes(20)).await;
+        assert_eq!(machine.poll(), Some(TimeoutEvent::Warning {
+            stage: 1,
+            closes_at: start + minutes(60),
+        }));
+        assert_eq!(machine.state(), State::Warned { stage: 1 });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn warned_to_expired() {
+        let mut machine = machine();
+
+        advance(minutes(30)).await;
+        machine.poll();
+        advance(minutes(20)).await;
+        machine.poll();
+        advance(minutes(10)).await;
+        assert_eq!(machine.poll(), Some(TimeoutEvent::Expired { stage: 2 }));
+        assert_eq!(machine.state(), State::Expired);
+        assert_eq!(machine.deadline(), None);
+        assert_eq!(machine.poll(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn single_stage_goes_straight_to_expired() {
+        let mut machine = TimeoutMachine::new(TokioClock, vec![minutes(10)]);
+
+        advance(minutes(10)).await;
+        assert_eq!(machine.poll(), Some(TimeoutEvent::Expired { stage: 0 }));
+        assert_eq!(machine.state(), State::Expired);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stages_that_are_overdue_are_emitted_one_at_a_time() {
+        let mut machine = machine();
+
+        advance(minutes(55)).await;
+        assert!(matches!(machine.poll(), Some(TimeoutEvent::Warning { stage: 0, .. })));
+        assert!(matches!(machine.poll(), Some(TimeoutEvent::Warning { stage: 1, .. })));
+        assert_eq!(machine.poll(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn warnings_are_skipped_once_the_thread_should_have_closed() {
+        let mut machine = machine();
+
+        advance(minutes(90)).await;
+        assert_eq!(machine.poll(), Some(TimeoutEvent::Expired { stage: 2 }));
+        assert_eq!(machine.state(), State::Expired);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_while_idle_restarts_the_clock() {
+        let mut machine = machine();
+
+        advance(minutes(20)).await;
+        machine.reset();
+        advance(minutes(20)).await;
+        assert_eq!(machine.poll(), None);
+        assert_eq!(machine.deadline(), Some(Instant::now() + minutes(10)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_while_warned_returns_to_idle() {
+        let mut machine = machine();
+
+        advance(minutes(30)).await;
+        machine.poll();
+        machine.reset();
+        assert_eq!(machine.state(), State::Idle);
+        assert_eq!(machine.deadline(), Some(Instant::now() + minutes(30)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_after_expired_is_ignored() {
+        let mut machine = TimeoutMachine::new(TokioClock, vec![minutes(10)]);
+
+        advance(minutes(10)).await;
+        machine.poll();
+        machine.reset();
+        assert_eq!(machine.state(), State::Expired);
+        assert_eq!(machine.deadline(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paused_clock_has_no_deadline() {
+        let mut machine = machine();
+
+        machine.pause();
+        assert_eq!(machine.deadline(), None);
+
+        advance(minutes(120)).await;
+        assert_eq!(machine.poll(), None);
+        assert_eq!(machine.state(), State::Idle);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_while_paused_stays_paused() {
+        let mut machine = machine();
+
+        machine.pause();
+        machine.reset();
+        assert_eq!(machine.deadline(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resume_runs_from_the_last_message_sent() {
+        let mut machine = machine();
+
+        machine.pause();
+        advance(minutes(5)).await;
+        let last_sent = Instant::now();
+        advance(minutes(5)).await;
+        machine.resume(Some(last_sent));
+        assert_eq!(machine.deadline(), Some(last_sent + minutes(30)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resume_without_a_message_runs_from_now() {
+        let mut machine = machine();
+        let before_pause = Instant::now();
+
+        machine.pause();
+        advance(minutes(5)).await;
+        machine.resume(Some(before_pause));
+        assert_eq!(machine.deadline(), Some(Instant::now() + minutes(30)));
+
+        machine.pause();
+        advance(minutes(5)).await;
+        machine.resume(None);
+        assert_eq!(machine.deadline(), Some(Instant::now() + minutes(30)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resume_while_warned_returns_to_idle() {
+        let mut machine = machine();
+
+        advance(minutes(30)).await;
+        machine.poll();
+        machine.pause();
+        assert_eq!(machine.state(), State::Warned { stage: 0 });
+
+        machine.resume(None);
+        assert_eq!(machine.state(), State::Idle);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resume_without_pause_is_ignored() {
+        let mut machine = machine();
+        let start = Instant::now();
+
+        advance(minutes(10)).await;
+        machine.resume(None);
+        assert_eq!(machine.deadline(), Some(start + minutes(30)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restore_continues_after_the_posted_stage() {
+        let mut machine = machine();
+        advance(minutes(40)).await;
+
+        machine.restore(minutes(40), Some(0));
+        assert_eq!(machine.state(), State::Warned { stage: 0 });
+        assert_eq!(machine.deadline(), Some(Instant::now() + minutes(10)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restore_without_posted_stage_is_idle() {
+        let mut machine = machine();
+        advance(minutes(40)).await;
+
+        machine.restore(minutes(40), None);
+        assert_eq!(machine.state(), State::Idle);
+        assert!(matches!(machine.poll(), Some(TimeoutEvent::Warning { stage: 0, .. })));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restore_after_last_stage_is_expired() {
+        let mut machine = machine();
+
+        machine.restore(Duration::ZERO, Some(2));
+        assert_eq!(machine.state(), State::Expired);
+        assert_eq!(machine.poll(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restore_further_back_than_the_clock_has_run() {
+        let mut machine = machine();
+
+        // Just after a reboot the monotonic clock has barely started
+        machine.restore(Duration::from_secs(60 * 60 * 24 * 365), None);
+        assert_eq!(machine.deadline(), Some(Instant::now()));
+        assert_eq!(machine.poll(), Some(TimeoutEvent::Expired { stage: 2 }));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_after_restore_uses_the_full_schedule() {
+        let mut machine = machine();
+
+        machine.restore(minutes(20), None);
+        assert_eq!(machine.deadline(), Some(Instant::now() + minutes(10)));
+
+        machine.reset();
+        assert_eq!(machine.deadline(), Some(Instant::now() + minutes(30)));
+    }
+}
@@ -0,0 +1,253 @@
+pub mod stages;
+pub mod machine;
+pub mod sink;
+
+use serenity::all::{ComponentInteraction, Context, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, GuildChannel, Message, Timestamp, UserId};
+use serenity::prelude::TypeMapKey;
+use serenity::http::Http;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::{error, info};
+use crate::timeout::machine::{Clock, State, TimeoutEvent, TimeoutMachine, TokioClock};
+use crate::timeout::sink::{DiscordSink, TimeoutSink, EXTENDED_TITLE};
+use crate::timeout::stages::Stages;
+
+pub const KEEP_OPEN_ID: &str = "timeout:keep_open";
+
+/// Closes the thread after a period of inactivity, posting each of the configured stages along the
+/// way.  The clock only runs while the thread is waiting on the user, it is paused while the agent
+/// is working on a turn.
+///
+/// The timing lives in [`TimeoutMachine`], this drives the machine and passes its events to a
+/// [`TimeoutSink`].
+pub struct Timeout<S = DiscordSink, C = TokioClock>
+where
+    S: TimeoutSink,
+    C: Clock + Clone,
+{
+    machine: Mutex<TimeoutMachine<C>>,
+    changed: Notify,
+    clock: C,
+    sink: S,
+}
+
+impl TypeMapKey for Timeout {
+    type Value = Arc<Timeout>;
+}
+
+impl Timeout {
+    pub fn new(
+        stages: Stages,
+        owner_id: UserId,
+        http: Arc<Http>,
+        channel: GuildChannel
+    ) -> Self {
+        let schedule = stages
+            .iter()
+            .map(|stage| stage.after)
+            .collect();
+
+        Self::with_sink(DiscordSink::new(stages, owner_id, http, channel), TokioClock, schedule)
+    }
+
+    /// Picks up where a previous run of this agent left off, so that restarting the agent does not
+    /// restart the clock.  The clock is started from the last activity in the thread, and stages
+    /// posted since then are not posted again.  Must be called before [`Timeout::run`].
+    ///
+    /// `messages` are the most recent messages in the thread, newest first
+    pub fn restore(&self, messages: &[Message], bot_id: UserId) {
+        let Some((last_activity, posted)) = self.sink.last_activity(messages, bot_id) else {
+            return;
+        };
+
+        let elapsed = Timestamp::now().unix_timestamp() - last_activity.unix_timestamp();
+        let elapsed = Duration::from_secs(elapsed.max(0) as u64);
+
+        info!("Restoring timeout from {last_activity}, last stage posted: {posted:?}");
+        self.update(|machine| machine.restore(elapsed, posted));
+    }
+
+    /// Handles a press of the keep open button on a timeout warning, restarting the clock without
+    /// passing anything on to the agent.  Returns false if the interaction was not for the timeout.
+    pub async fn handle_interaction(&self, ctx: &Context, interaction: &ComponentInteraction) -> bool {
+        if interaction.data.custom_id != KEEP_OPEN_ID {
+            return false;
+        }
+
+        self.reset();
+
+        let embed = CreateEmbed::new()
+            .title(EXTENDED_TITLE)
+            .description(format!("<@{}> kept this thread open", interaction.user.id));
+        let response = CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .components(vec![]));
+
+        if let Err(e) = interaction.create_response(&ctx.http, response).await {
+            error!("Error responding to keep open interaction: {e}");
+        }
+
+        true
+    }
+}
+
+impl<S, C> Timeout<S, C>
+where
+    S: TimeoutSink,
+    C: Clock + Clone,
+{
+    /// `schedule` is when each stage is due after the thread became inactive, see
+    /// [`TimeoutMachine::new`]
+    pub fn with_sink(sink: S, clock: C, schedule: Vec<Duration>) -> Self {
+        Self {
+            machine: Mutex::new(TimeoutMachine::new(clock.clone(), schedule)),
+            changed: Notify::new(),
+            clock,
+            sink,
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut TimeoutMachine<C>)) {
+        f(&mut self.machine.lock().unwrap());
+        self.changed.notify_waiters();
+    }
+
+    /// Restarts the clock from now.  If the clock is paused, it stays paused
+    pub fn reset(&self) {
+        self.update(|machine| machine.reset());
+    }
+
+    /// Stops the clock, used while the agent is working on a turn
+    pub fn pause(&self) {
+        self.update(|machine| machine.pause());
+    }
+
+    /// Restarts the clock after a turn, see [`TimeoutMachine::resume`]
+    pub fn resume(&self, last_sent: Option<Instant>) {
+        self.update(|machine| machine.resume(last_sent));
+    }
+
+    /// Sends each stage to the sink as the thread stays inactive, returning once the thread has
+    /// expired
+    pub async fn run(&self) {
+        loop {
+            // Created before reading the machine so that no change is missed
+            let changed = self.changed.notified();
+
+            let (state, deadline) = {
+                let machine = self.machine.lock().unwrap();
+                (machine.state(), machine.deadline())
+            };
+
+            if state == State::Expired {
+                break;
+            }
+
+            match deadline {
+                Some(deadline) => select! {
+                    _ = self.clock.sleep_until(deadline) => {},
+                    _ = changed => continue,
+                },
+                None => {
+                    changed.await;
+                    continue;
+                }
+            }
+
+            let event = self.machine.lock().unwrap().poll();
+            if let Some(event) = event {
+                self.sink.send(event).await;
+                if let TimeoutEvent::Expired { .. } = event {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::sleep;
+
+    /// Records events along with when they were sent
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<(Instant, TimeoutEvent)>>,
+    }
+
+    impl TimeoutSink for RecordingSink {
+        async fn send(&self, event: TimeoutEvent) {
+            self.events.lock().unwrap().push((Instant::now(), event));
+        }
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn timeout() -> Arc<Timeout<RecordingSink>> {
+        Arc::new(Timeout::with_sink(RecordingSink::default(), TokioClock, vec![minutes(30), minutes(60)]))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn run_sends_every_stage_then_returns() {
+        let start = Instant::now();
+        let timeout = timeout();
+        timeout.run().await;
+
+        assert_eq!(*timeout.sink.events.lock().unwrap(), vec![
+            (start + minutes(30), TimeoutEvent::Warning { stage: 0, closes_at: start + minutes(60) }),
+            (start + minutes(60), TimeoutEvent::Expired { stage: 1 }),
+        ]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn run_restarts_after_reset() {
+        let start = Instant::now();
+        let timeout = timeout();
+        let handle = tokio::spawn({
+            let timeout = timeout.clone();
+            async move { timeout.run().await }
+        });
+
+        // Sleeping rather than advancing lets the runtime run the timeout at each of its deadlines
+        sleep(minutes(40)).await;
+        timeout.reset();
+        handle.await.unwrap();
+
+        let events = timeout.sink.events.lock().unwrap();
+        assert_eq!(*events, vec![
+            (start + minutes(30), TimeoutEvent::Warning { stage: 0, closes_at: start + minutes(60) }),
+            (start + minutes(70), TimeoutEvent::Warning { stage: 0, closes_at: start + minutes(100) }),
+            (start + minutes(100), TimeoutEvent::Expired { stage: 1 }),
+        ]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn run_waits_while_paused() {
+        let start = Instant::now();
+        let timeout = timeout();
+        timeout.pause();
+
+        let handle = tokio::spawn({
+            let timeout = timeout.clone();
+            async move { timeout.run().await }
+        });
+
+        sleep(minutes(120)).await;
+        assert!(timeout.sink.events.lock().unwrap().is_empty());
+
+        timeout.resume(None);
+        handle.await.unwrap();
+
+        let events = timeout.sink.events.lock().unwrap();
+        assert_eq!(*events, vec![
+            (start + minutes(150), TimeoutEvent::Warning { stage: 0, closes_at: start + minutes(180) }),
+            (start + minutes(180), TimeoutEvent::Expired { stage: 1 }),
+        ]);
+    }
+}
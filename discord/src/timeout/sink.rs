@@ -0,0 +1,204 @@
+use std::future::Future;
+use std::sync::Arc;
+use serenity::all::{ButtonStyle, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateMessage, GuildChannel, Http, Message, Timestamp, UserId};
+use tokio::time::Instant;
+use tracing::error;
+use crate::timeout::machine::TimeoutEvent;
+use crate::timeout::stages::{Stages, CLOSES_VARIABLE, OWNER_VARIABLE};
+use crate::timeout::KEEP_OPEN_ID;
+
+/// Title a stage notification is edited to once the keep open button is pressed
+pub const EXTENDED_TITLE: &str = "⏱️ Thread extended";
+
+/// Stage notifications are marked with their index in the footer, so that they can be found again
+/// after a restart whatever their title renders to
+const STAGE_FOOTER_PREFIX: &str = "Inactivity notice ";
+
+fn stage_footer(index: usize, count: usize) -> String {
+    format!("{STAGE_FOOTER_PREFIX}{} of {count}", index + 1)
+}
+
+fn parse_stage_footer(footer: &str) -> Option<usize> {
+    let (number, _) = footer.strip_prefix(STAGE_FOOTER_PREFIX)?.split_once(" of ")?;
+    number.parse::<usize>().ok()?.checked_sub(1)
+}
+
+/// Where the events from [`crate::timeout::machine::TimeoutMachine`] go
+pub trait TimeoutSink: Send + Sync {
+    fn send(&self, event: TimeoutEvent) -> impl Future<Output = ()> + Send;
+}
+
+/// Posts the configured stage notifications to the thread
+pub struct DiscordSink {
+    stages: Stages,
+    owner_id: UserId,
+    http: Arc<Http>,
+    channel: GuildChannel,
+}
+
+impl DiscordSink {
+    pub fn new(
+        stages: Stages,
+        owner_id: UserId,
+        http: Arc<Http>,
+        channel: GuildChannel
+    ) -> Self {
+        Self {
+            stages,
+            owner_id,
+            http,
+            channel,
+        }
+    }
+
+    /// Finds when the thread was last active and the last stage posted since then, from the most
+    /// recent messages in the thread, newest first
+    pub fn last_activity(&self, messages: &[Message], bot_id: UserId) -> Option<(Timestamp, Option<usize>)> {
+        let mut posted = None;
+        for message in messages {
+            let embed = message.embeds
+                .first()
+                .filter(|_| message.author.id == bot_id);
+
+            // A stage that the keep open button was pressed on is edited, the clock was reset when
+            // it was pressed
+            if embed.and_then(|embed| embed.title.as_deref()) == Some(EXTENDED_TITLE) {
+                return Some((message.edited_timestamp.unwrap_or(message.timestamp), posted));
+            }
+
+            let stage = embed
+                .and_then(|embed| embed.footer.as_ref())
+                .and_then(|footer| parse_stage_footer(&footer.text))
+                .filter(|stage| self.stages.get(*stage).is_some());
+
+            match stage {
+                Some(stage) => posted = posted.max(Some(stage)),
+                None => return Some((message.timestamp, posted)),
+            }
+        }
+
+        None
+    }
+}
+
+impl TimeoutSink for DiscordSink {
+    async fn send(&self, event: TimeoutEvent) {
+        let (index, closes_at) = match event {
+            TimeoutEvent::Warning { stage, closes_at } => (stage, closes_at),
+            TimeoutEvent::Expired { stage } => (stage, Instant::now()),
+        };
+
+        let Some(stage) = self.stages.get(index) else {
+            error!("Timeout stage {index} does not exist");
+            return;
+        };
+
+        let remaining = closes_at.saturating_duration_since(Instant::now());
+        let closes = format!("<t:{}:R>", Timestamp::now().unix_timestamp() + remaining.as_secs() as i64);
+        let owner = format!("<@{}>", self.owner_id);
+        let values = [(OWNER_VARIABLE, owner.as_str()), (CLOSES_VARIABLE, closes.as_str())];
+
+        let mut embed = CreateEmbed::new()
+            .title(stage.title.render(&values))
+            .description(stage.message.render(&values))
+            .footer(CreateEmbedFooter::new(stage_footer(index, self.stages.iter().count())));
+        if let Some(color) = stage.color {
+            embed = embed.colour(color);
+        }
+
+        let mut message = CreateMessage::new()
+            .embed(embed);
+        if stage.keep_open {
+            message = message.components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(KEEP_OPEN_ID)
+                    .label("Keep this thread open")
+                    .style(ButtonStyle::Primary)
+            ])]);
+        }
+
+        if let Err(e) = self.channel.send_message(&self.http, message).await {
+            error!("Error sending timeout notification: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use serenity::all::Embed;
+
+    const BOT: UserId = UserId::new(1);
+    const OWNER: UserId = UserId::new(2);
+
+    fn sink() -> DiscordSink {
+        let stages = Stages::single_warning(Duration::from_secs(30 * 60), Duration::from_secs(30 * 60));
+        DiscordSink::new(stages, OWNER, Arc::new(Http::new("")), GuildChannel::default())
+    }
+
+    fn ago(minutes: i64) -> Timestamp {
+        Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp() - minutes * 60).unwrap()
+    }
+
+    fn message(author: UserId, minutes_ago: i64, embed: Option<serde_json::Value>) -> Message {
+        let mut message = Message::default();
+        message.author.id = author;
+        message.timestamp = ago(minutes_ago);
+        message.embeds = embed
+            .into_iter()
+            .map(|embed| serde_json::from_value::<Embed>(embed).unwrap())
+            .collect();
+        message
+    }
+
+    fn stage(index: usize, minutes_ago: i64) -> Message {
+        // The title of a stage can change with {closes}, only the footer is stable
+        message(BOT, minutes_ago, Some(serde_json::json!({
+            "title": format!("Closes <t:{}:R>", minutes_ago),
+            "footer": { "text": stage_footer(index, 2) },
+        })))
+    }
+
+    #[test]
+    fn footers_round_trip() {
+        assert_eq!(parse_stage_footer(&stage_footer(0, 3)), Some(0));
+        assert_eq!(parse_stage_footer(&stage_footer(2, 3)), Some(2));
+        assert_eq!(parse_stage_footer("Inactivity notice 0 of 3"), None);
+        assert_eq!(parse_stage_footer("Something else"), None);
+    }
+
+    #[test]
+    fn last_message_is_the_last_activity() {
+        let messages = [message(OWNER, 5, None), message(BOT, 10, None)];
+        assert_eq!(sink().last_activity(&messages, BOT), Some((messages[0].timestamp, None)));
+    }
+
+    #[test]
+    fn posted_stages_are_skipped() {
+        let messages = [stage(0, 5), message(BOT, 40, None)];
+        assert_eq!(sink().last_activity(&messages, BOT), Some((messages[1].timestamp, Some(0))));
+    }
+
+    #[test]
+    fn extended_stage_is_the_last_activity() {
+        let mut extended = message(BOT, 20, Some(serde_json::json!({ "title": EXTENDED_TITLE })));
+        let edited = ago(10);
+        extended.edited_timestamp = Some(edited);
+        let messages = [extended, message(OWNER, 60, None)];
+        assert_eq!(sink().last_activity(&messages, BOT), Some((edited, None)));
+    }
+
+    #[test]
+    fn stage_footers_from_other_users_are_ignored() {
+        let mut copied = stage(0, 5);
+        copied.author.id = OWNER;
+        let messages = [copied];
+        assert_eq!(sink().last_activity(&messages, BOT), Some((messages[0].timestamp, None)));
+    }
+
+    #[test]
+    fn only_stages_is_no_activity() {
+        assert_eq!(sink().last_activity(&[stage(0, 5)], BOT), None);
+        assert_eq!(sink().last_activity(&[], BOT), None);
+    }
+}
@@ -0,0 +1,232 @@
+use std::path::Path;
+use std::time::Duration;
+use serde::{Deserialize, Deserializer};
+use serenity::all::Colour;
+use crate::discord::tools::embed::parse_color;
+use crate::template::{Template, TemplateError};
+
+/// Variables available in stage titles and messages
+pub const OWNER_VARIABLE: &str = "owner";
+pub const CLOSES_VARIABLE: &str = "closes";
+const VARIABLES: &[&str] = &[OWNER_VARIABLE, CLOSES_VARIABLE];
+
+/// One notification posted while the thread is inactive.  The last stage closes the thread.
+pub struct Stage {
+    /// How long after the thread became inactive this stage is posted
+    pub after: Duration,
+    pub title: Template,
+    pub message: Template,
+    pub color: Option<Colour>,
+
+    /// Whether the keep open button is shown with this stage's message
+    pub keep_open: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StagesError {
+    #[error("could not read timeout stages: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("could not parse timeout stages: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("stage {stage}: {error}")]
+    Template {
+        stage: usize,
+        error: TemplateError,
+    },
+
+    #[error("stage {0}: {1}")]
+    Invalid(usize, String),
+
+    #[error("at least one timeout stage is required")]
+    Empty,
+}
+
+#[derive(Deserialize)]
+struct StagesFile {
+    #[serde(rename = "stage")]
+    stages: Vec<StageConfig>,
+}
+
+#[derive(Deserialize)]
+struct StageConfig {
+    #[serde(deserialize_with = "deserialize_duration")]
+    after: Duration,
+    title: String,
+    message: String,
+    color: Option<String>,
+    keep_open: Option<bool>,
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let duration = String::deserialize(deserializer)?;
+    humantime::parse_duration(&duration).map_err(serde::de::Error::custom)
+}
+
+/// The notifications posted while a thread is inactive, in the order they are posted
+pub struct Stages {
+    stages: Vec<Stage>,
+}
+
+impl Stages {
+    /// Loads stages from a TOML file with one `[[stage]]` table per stage
+    pub fn load(path: &Path) -> Result<Self, StagesError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    fn parse(source: &str) -> Result<Self, StagesError> {
+        let file: StagesFile = toml::from_str(source)?;
+        if file.stages.is_empty() {
+            return Err(StagesError::Empty);
+        }
+
+        let mut stages: Vec<Stage> = Vec::new();
+        for (i, config) in file.stages.into_iter().enumerate() {
+            let template = |source: &str| Template::parse(source, VARIABLES)
+                .map_err(|error| StagesError::Template { stage: i, error });
+
+            if let Some(previous) = stages.last()
+                && previous.after >= config.after {
+                return Err(StagesError::Invalid(i, "stages must be in order, each after the last".to_string()));
+            }
+
+            let color = config.color
+                .as_deref()
+                .map(parse_color)
+                .transpose()
+                .map_err(|e| StagesError::Invalid(i, e))?;
+
+            stages.push(Stage {
+                after: config.after,
+                title: template(&config.title)?,
+                message: template(&config.message)?,
+                color,
+                keep_open: config.keep_open.unwrap_or(true),
+            });
+        }
+
+        // The thread is closed once the last stage is posted, so there is nothing to keep open
+        if let Some(last) = stages.last_mut() {
+            last.keep_open = false;
+        }
+
+        Ok(Self {
+            stages
+        })
+    }
+
+    /// A single warning followed by the closing message, used when no stages are configured
+    pub fn single_warning(warning: Duration, timeout: Duration) -> Self {
+        let template = |source| Template::parse(source, VARIABLES).unwrap();
+
+        Self {
+            stages: vec![
+                Stage {
+                    after: warning,
+                    title: template("⚠️ Timeout warning"),
+                    message: template("This thread will be closed automatically for inactivity {closes}"),
+                    color: None,
+                    keep_open: true,
+                },
+                Stage {
+                    after: warning + timeout,
+                    title: template("⚠️ Timeout"),
+                    message: template("This thread has been closed due to inactivity"),
+                    color: None,
+                    keep_open: false,
+                },
+            ]
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Stage> {
+        self.stages.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Stage> {
+        self.stages.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn stages_are_parsed_in_order() {
+        let stages = Stages::parse(r##"
+            [[stage]]
+            after = "30m"
+            title = "Still there?"
+            message = "<@{owner}>, this thread closes {closes}"
+            color = "#FEE75C"
+
+            [[stage]]
+            after = "1h"
+            title = "Closed"
+            message = "Closed for inactivity"
+            keep_open = true
+        "##).unwrap();
+
+        let stages = stages.iter().collect::<Vec<_>>();
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages[0].after, minutes(30));
+        assert_eq!(stages[0].message.render(&[(OWNER_VARIABLE, "1"), (CLOSES_VARIABLE, "soon")]),
+            "<@1>, this thread closes soon");
+        assert_eq!(stages[0].color, Some(Colour::new(0xFEE75C)));
+        assert!(stages[0].keep_open);
+        assert_eq!(stages[1].after, minutes(60));
+        assert!(!stages[1].keep_open);
+    }
+
+    #[test]
+    fn empty_stage_list() {
+        assert!(matches!(Stages::parse("stage = []"), Err(StagesError::Empty)));
+    }
+
+    #[test]
+    fn stages_out_of_order() {
+        let result = Stages::parse(r#"
+            [[stage]]
+            after = "1h"
+            title = "Warning"
+            message = "Closes soon"
+
+            [[stage]]
+            after = "1h"
+            title = "Closed"
+            message = "Closed"
+        "#);
+        assert!(matches!(result, Err(StagesError::Invalid(1, _))));
+    }
+
+    #[test]
+    fn unknown_variable_names_the_stage() {
+        let result = Stages::parse(r#"
+            [[stage]]
+            after = "1h"
+            title = "Closed"
+            message = "Bye {ownr}"
+        "#);
+        assert!(matches!(result, Err(StagesError::Template { stage: 0, error: TemplateError::UnknownVariable { .. } })));
+    }
+
+    #[test]
+    fn invalid_values() {
+        let stage = |after: &str, color: &str| format!(r#"
+            [[stage]]
+            after = "{after}"
+            title = "Closed"
+            message = "Closed"
+            color = "{color}"
+        "#);
+
+        assert!(matches!(Stages::parse(&stage("soon", "#FFFFFF")), Err(StagesError::Parse(_))));
+        assert!(matches!(Stages::parse(&stage("1h", "red")), Err(StagesError::Invalid(0, _))));
+    }
+}
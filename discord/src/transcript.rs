@@ -0,0 +1,335 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use coral_rs::rig::completion::{AssistantContent, Message};
+use coral_rs::rig::message::{ToolResultContent, UserContent};
+use serde::Serialize;
+use serenity::all::{ChannelId, ComponentInteraction, Message as DiscordMessage, MessageId, Timestamp, UserId};
+use crate::discord::shutdown::ShutdownReason;
+use crate::discord::thread_message::ThreadMessage;
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptEntry {
+    /// A message posted in the thread and passed to the agent
+    Message {
+        timestamp: Timestamp,
+        #[serde(flatten)]
+        message: ThreadMessage,
+    },
+
+    /// A message the bot posted in the thread, either by the agent or by the bot itself, such as
+    /// timeout warnings, close requests, escalation notices and the survey
+    BotMessage {
+        timestamp: Timestamp,
+        id: MessageId,
+        content: String,
+        embeds: Vec<TranscriptEmbed>,
+    },
+
+    /// A button pressed in the thread
+    Interaction {
+        timestamp: Timestamp,
+        user: UserId,
+        custom_id: String,
+    },
+
+    /// Text the agent responded with outside of a tool call, this is not posted in the thread
+    AgentText {
+        timestamp: Timestamp,
+        text: String,
+    },
+
+    ToolCall {
+        timestamp: Timestamp,
+        id: String,
+        name: String,
+        arguments: serde_json::Value,
+    },
+
+    ToolResult {
+        timestamp: Timestamp,
+        id: String,
+        result: String,
+    },
+}
+
+#[derive(Serialize)]
+pub struct TranscriptEmbed {
+    title: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TranscriptFile<'a> {
+    thread_id: ChannelId,
+    title: &'a str,
+    owner_id: UserId,
+    session_id: Option<&'a str>,
+    started: Timestamp,
+    ended: Timestamp,
+    reason: String,
+    entries: &'a [TranscriptEntry],
+}
+
+/// Everything that happened in the thread while the agent was running: the messages it was given,
+/// what it said, every tool it called, everything the bot posted and the buttons pressed.  Written
+/// out as JSON and Markdown when the agent shuts down, whatever the reason.
+pub struct Transcript {
+    thread_id: ChannelId,
+    title: String,
+    owner_id: UserId,
+    session_id: Option<String>,
+    started: Timestamp,
+    entries: Mutex<Vec<TranscriptEntry>>,
+}
+
+impl Transcript {
+    pub fn new(thread_id: ChannelId, title: String, owner_id: UserId, session_id: Option<String>) -> Self {
+        Self {
+            thread_id,
+            title,
+            owner_id,
+            session_id,
+            started: Timestamp::now(),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn record_message(&self, message: &ThreadMessage) {
+        self.entries.lock().unwrap().push(TranscriptEntry::Message {
+            timestamp: message.id.created_at(),
+            message: message.clone(),
+        });
+    }
+
+    pub fn record_bot_message(&self, message: &DiscordMessage) {
+        self.entries.lock().unwrap().push(TranscriptEntry::BotMessage {
+            timestamp: message.timestamp,
+            id: message.id,
+            content: message.content.clone(),
+            embeds: message.embeds
+                .iter()
+                .map(|embed| TranscriptEmbed {
+                    title: embed.title.clone(),
+                    description: embed.description.clone(),
+                })
+                .collect(),
+        });
+    }
+
+    pub fn record_interaction(&self, interaction: &ComponentInteraction) {
+        self.entries.lock().unwrap().push(TranscriptEntry::Interaction {
+            timestamp: Timestamp::now(),
+            user: interaction.user.id,
+            custom_id: interaction.data.custom_id.clone(),
+        });
+    }
+
+    /// Records the messages a completion added to the conversation: the agent's response and the
+    /// results of the tools it called
+    pub fn record_completion(&self, messages: &[Message]) {
+        let timestamp = Timestamp::now();
+        let mut entries = self.entries.lock().unwrap();
+        for message in messages {
+            match message {
+                Message::Assistant { content, .. } => {
+                    for content in content.iter() {
+                        match content {
+                            AssistantContent::Text(text) => entries.push(TranscriptEntry::AgentText {
+                                timestamp,
+                                text: text.text.clone(),
+                            }),
+                            AssistantContent::ToolCall(call) => entries.push(TranscriptEntry::ToolCall {
+                                timestamp,
+                                id: call.id.clone(),
+                                name: call.function.name.clone(),
+                                arguments: call.function.arguments.clone(),
+                            }),
+                            AssistantContent::Reasoning(_) => {}
+                        }
+                    }
+                }
+                Message::User { content } => {
+                    for content in content.iter() {
+                        if let UserContent::ToolResult(result) = content {
+                            entries.push(TranscriptEntry::ToolResult {
+                                timestamp,
+                                id: result.id.clone(),
+                                result: result.content
+                                    .iter()
+                                    .map(|content| match content {
+                                        ToolResultContent::Text(text) => text.text.as_str(),
+                                        ToolResultContent::Image(_) => "[image]",
+                                    })
+                                    .collect::<Vec<_>>()
+                                    .join(""),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn file<'a>(&'a self, entries: &'a [TranscriptEntry], reason: ShutdownReason) -> TranscriptFile<'a> {
+        TranscriptFile {
+            thread_id: self.thread_id,
+            title: &self.title,
+            owner_id: self.owner_id,
+            session_id: self.session_id.as_deref(),
+            started: self.started,
+            ended: Timestamp::now(),
+            reason: format!("{reason:?}"),
+            entries,
+        }
+    }
+
+    fn markdown(file: &TranscriptFile) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# {}\n", file.title);
+        let _ = writeln!(md, "- Thread: {}", file.thread_id);
+        let _ = writeln!(md, "- Owner: {}", file.owner_id);
+        let _ = writeln!(md, "- Coral session: {}", file.session_id.unwrap_or("none"));
+        let _ = writeln!(md, "- Started: {}", file.started);
+        let _ = writeln!(md, "- Ended: {} ({})", file.ended, file.reason);
+
+        for entry in file.entries {
+            match entry {
+                TranscriptEntry::Message { timestamp, message } => {
+                    let _ = writeln!(md, "\n## {timestamp} <@{}>\n", message.sender);
+                    match &message.choice {
+                        Some(choice) => {
+                            let _ = writeln!(md, "Chose \"{}\" for \"{}\"", choice.label, choice.question);
+                        }
+                        None => {
+                            let _ = writeln!(md, "{}", message.content);
+                        }
+                    }
+                }
+                TranscriptEntry::BotMessage { timestamp, content, embeds, .. } => {
+                    let _ = writeln!(md, "\n## {timestamp} Bot\n");
+                    if !content.is_empty() {
+                        let _ = writeln!(md, "{content}");
+                    }
+
+                    for embed in embeds {
+                        let title = embed.title.as_deref().unwrap_or_default();
+                        let description = embed.description.as_deref().unwrap_or_default();
+                        let _ = writeln!(md, "> **{title}**\n> {}", description.replace('\n', "\n> "));
+                    }
+                }
+                TranscriptEntry::Interaction { timestamp, user, custom_id } => {
+                    let _ = writeln!(md, "\n### {timestamp} <@{user}> pressed `{custom_id}`");
+                }
+                TranscriptEntry::AgentText { timestamp, text } => {
+                    let _ = writeln!(md, "\n## {timestamp} Agent\n\n{text}");
+                }
+                TranscriptEntry::ToolCall { timestamp, id, name, arguments } => {
+                    let arguments = serde_json::to_string_pretty(arguments).unwrap_or_default();
+                    let _ = writeln!(md, "\n### {timestamp} Tool call `{name}` ({id})\n\n```json\n{arguments}\n```");
+                }
+                TranscriptEntry::ToolResult { timestamp, id, result } => {
+                    let _ = writeln!(md, "\n### {timestamp} Tool result ({id})\n\n```\n{result}\n```");
+                }
+            }
+        }
+
+        md
+    }
+
+    /// Writes the transcript to `<dir>/<thread id>-<unix time>.json` and `.md`, returning the path
+    /// of the JSON file
+    pub fn export(&self, dir: &Path, reason: ShutdownReason) -> std::io::Result<PathBuf> {
+        let entries = self.entries.lock().unwrap();
+        let file = self.file(&entries, reason);
+
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}-{}", self.thread_id, file.ended.unix_timestamp()));
+        let json_path = path.with_extension("json");
+        std::fs::write(&json_path, serde_json::to_string_pretty(&file)?)?;
+        std::fs::write(path.with_extension("md"), Self::markdown(&file))?;
+
+        Ok(json_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coral_rs::rig::OneOrMany;
+    use serenity::all::MessageId;
+
+    fn transcript() -> Transcript {
+        let transcript = Transcript::new(ChannelId::new(1), "Agent does not start".to_string(), UserId::new(2), Some("session".to_string()));
+        transcript.record_message(&ThreadMessage {
+            id: MessageId::new(175928847299117063),
+            sender: UserId::new(2),
+            content: "It crashes on startup".to_string(),
+            choice: None,
+        });
+        let timeout: DiscordMessage = serde_json::from_value(serde_json::json!({
+            "id": "175928847299117064",
+            "channel_id": "1",
+            "author": { "id": "3", "username": "bot", "discriminator": "0000", "avatar": null },
+            "content": "",
+            "timestamp": "2025-01-01T00:00:00Z",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [{ "title": "⚠️ Timeout warning", "description": "This thread will be closed" }],
+            "pinned": false,
+            "type": 0
+        })).unwrap();
+        transcript.record_bot_message(&timeout);
+        transcript.record_completion(&[
+            Message::Assistant {
+                id: None,
+                content: OneOrMany::one(AssistantContent::tool_call("call_1", "send_discord_message", serde_json::json!({
+                    "content": "Which version?"
+                }))),
+            },
+            UserContent::tool_result("call_1", OneOrMany::one("{\"id\":3}".to_string().into())).into(),
+        ]);
+
+        transcript
+    }
+
+    #[test]
+    fn completions_are_recorded_as_tool_calls_and_results() {
+        let transcript = transcript();
+        let entries = transcript.entries.lock().unwrap();
+        let file = transcript.file(&entries, ShutdownReason::Timeout);
+        let json = serde_json::to_value(&file).unwrap();
+
+        assert_eq!(json["session_id"], "session");
+        assert_eq!(json["reason"], "Timeout");
+        assert_eq!(json["entries"][0]["type"], "message");
+        assert_eq!(json["entries"][0]["content"], "It crashes on startup");
+        assert_eq!(json["entries"][1]["type"], "bot_message");
+        assert_eq!(json["entries"][1]["embeds"][0]["title"], "⚠️ Timeout warning");
+        assert_eq!(json["entries"][2]["type"], "tool_call");
+        assert_eq!(json["entries"][2]["name"], "send_discord_message");
+        assert_eq!(json["entries"][2]["arguments"]["content"], "Which version?");
+        assert_eq!(json["entries"][3]["type"], "tool_result");
+        assert_eq!(json["entries"][3]["result"], "{\"id\":3}");
+    }
+
+    #[test]
+    fn markdown_has_every_entry() {
+        let transcript = transcript();
+        let entries = transcript.entries.lock().unwrap();
+        let md = Transcript::markdown(&transcript.file(&entries, ShutdownReason::Resolved));
+
+        assert!(md.starts_with("# Agent does not start\n"));
+        assert!(md.contains("- Coral session: session"));
+        assert!(md.contains("<@2>\n\nIt crashes on startup"));
+        assert!(md.contains("Bot\n\n> **⚠️ Timeout warning**\n> This thread will be closed"));
+        assert!(md.contains("Tool call `send_discord_message` (call_1)"));
+        assert!(md.contains("\"content\": \"Which version?\""));
+        assert!(md.contains("Tool result (call_1)\n\n```\n{\"id\":3}\n```"));
+    }
+}
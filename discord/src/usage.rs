@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use coral_rs::rig::completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Usage};
+use coral_rs::rig::streaming::StreamingCompletionResponse;
+use serde::Deserialize;
+use tracing::info;
+
+/// Tokens used by one or more completions
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt: u64,
+    pub completion: u64,
+}
+
+impl TokenUsage {
+    fn add(&mut self, usage: TokenUsage) {
+        self.prompt += usage.prompt;
+        self.completion += usage.completion;
+    }
+}
+
+/// The price of a model in USD per million tokens
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Price {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl Price {
+    pub fn cost(&self, usage: TokenUsage) -> f64 {
+        (usage.prompt as f64 * self.prompt + usage.completion as f64 * self.completion) / 1_000_000.0
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PricesError {
+    #[error("could not read price table: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("could not parse price table: {0}")]
+    Parse(#[from] toml::de::Error),
+}
+
+/// Model prices, loaded from a TOML file with a `[models."<model id>"]` table per model
+#[derive(Deserialize)]
+pub struct Prices {
+    models: HashMap<String, Price>,
+}
+
+impl Prices {
+    pub fn load(path: &Path) -> Result<Self, PricesError> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn get(&self, model: &str) -> Option<Price> {
+        self.models.get(model).copied()
+    }
+}
+
+#[derive(Default)]
+struct Totals {
+    turn: TokenUsage,
+    total: TokenUsage,
+    turns: u32,
+    completions: u32,
+}
+
+/// Counts the tokens used by the agent in this thread, per turn and in total.  The cost is only
+/// known if there is a price for the model
+pub struct UsageTracker {
+    price: Option<Price>,
+    totals: Mutex<Totals>,
+}
+
+impl UsageTracker {
+    pub fn new(price: Option<Price>) -> Self {
+        Self {
+            price,
+            totals: Mutex::new(Totals::default()),
+        }
+    }
+
+    pub fn record(&self, usage: &Usage) {
+        let usage = TokenUsage {
+            prompt: usage.input_tokens,
+            completion: usage.output_tokens,
+        };
+
+        let mut totals = self.totals.lock().unwrap();
+        totals.turn.add(usage);
+        totals.total.add(usage);
+        totals.completions += 1;
+    }
+
+    /// Logs the usage of the turn that just finished, if anything was used
+    pub fn finish_turn(&self) {
+        let mut totals = self.totals.lock().unwrap();
+        if totals.turn == TokenUsage::default() {
+            return;
+        }
+
+        totals.turns += 1;
+        let turn = std::mem::take(&mut totals.turn);
+        match self.price {
+            Some(price) => info!("Turn {} used {} prompt and {} completion tokens (${:.4})",
+                totals.turns, turn.prompt, turn.completion, price.cost(turn)),
+            None => info!("Turn {} used {} prompt and {} completion tokens",
+                totals.turns, turn.prompt, turn.completion),
+        }
+    }
+
+    pub fn total(&self) -> TokenUsage {
+        self.totals.lock().unwrap().total
+    }
+
+    /// What the thread has cost so far in USD
+    pub fn cost(&self) -> Option<f64> {
+        self.price.map(|price| price.cost(self.total()))
+    }
+
+    /// Logs the totals for the thread, called when the agent shuts down
+    pub fn log_totals(&self) {
+        let totals = self.totals.lock().unwrap();
+        let cost = self.price
+            .map(|price| format!(", ${:.4}", price.cost(totals.total)))
+            .unwrap_or_default();
+
+        info!("Thread used {} prompt and {} completion tokens over {} turns and {} completions{cost}",
+            totals.total.prompt, totals.total.completion, totals.turns, totals.completions);
+    }
+}
+
+/// Records the usage of every completion made with the model in a [`UsageTracker`]
+#[derive(Clone)]
+pub struct MeteredModel<M: CompletionModel> {
+    model: M,
+    usage: Arc<UsageTracker>,
+}
+
+impl<M: CompletionModel> MeteredModel<M> {
+    pub fn new(model: M, usage: Arc<UsageTracker>) -> Self {
+        Self {
+            model,
+            usage,
+        }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for MeteredModel<M> {
+    type Response = M::Response;
+    type StreamingResponse = M::StreamingResponse;
+
+    async fn completion(&self, request: CompletionRequest) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let response = self.model.completion(request).await?;
+        self.usage.record(&response.usage);
+        Ok(response)
+    }
+
+    /// Not metered, the agent does not stream
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        self.model.stream(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt: u64, completion: u64) -> Usage {
+        Usage {
+            input_tokens: prompt,
+            output_tokens: completion,
+            total_tokens: prompt + completion,
+        }
+    }
+
+    #[test]
+    fn cost_is_per_million_tokens() {
+        let price = Price {
+            prompt: 0.4,
+            completion: 1.6,
+        };
+
+        let cost = price.cost(TokenUsage {
+            prompt: 500_000,
+            completion: 250_000,
+        });
+        assert!((cost - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn totals_carry_across_turns() {
+        let tracker = UsageTracker::new(Some(Price {
+            prompt: 1.0,
+            completion: 2.0,
+        }));
+
+        tracker.record(&usage(1000, 100));
+        tracker.finish_turn();
+        tracker.record(&usage(2000, 200));
+        tracker.record(&usage(3000, 300));
+        tracker.finish_turn();
+
+        assert_eq!(tracker.total(), TokenUsage {
+            prompt: 6000,
+            completion: 600,
+        });
+        assert!((tracker.cost().unwrap() - 0.0072).abs() < 1e-9);
+        assert_eq!(tracker.totals.lock().unwrap().turns, 2);
+    }
+
+    #[test]
+    fn cost_is_unknown_without_a_price() {
+        let tracker = UsageTracker::new(None);
+        tracker.record(&usage(1000, 100));
+        assert_eq!(tracker.cost(), None);
+    }
+
+    #[test]
+    fn prices_are_read_by_model_id() {
+        let prices: Prices = toml::from_str(r#"
+            [models."openai/gpt-4.1-mini"]
+            prompt = 0.4
+            completion = 1.6
+        "#).unwrap();
+
+        assert_eq!(prices.get("openai/gpt-4.1-mini"), Some(Price {
+            prompt: 0.4,
+            completion: 1.6,
+        }));
+        assert_eq!(prices.get("openai/gpt-4.1"), None);
+    }
+}